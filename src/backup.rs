@@ -0,0 +1,255 @@
+use crate::config_observer::{
+    AppConfig, SshHost, add_hosts_to_config, get_keyring_password, load_app_config, load_hosts,
+    save_app_config, update_host_in_config,
+};
+use crate::crypto::FileKey;
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// File signature of an encrypted backup.
+const MAGIC: &[u8; 8] = b"RMSBKUP1";
+/// Suffix added to restored hosts whose alias is taken, for [`ConflictPolicy::KeepBoth`].
+const RESTORED_SUFFIX: &str = "restored";
+
+/// Everything a backup carries. Folders and tags travel on the hosts.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BackupBundle {
+    pub created_at: i64,
+    pub hosts: Vec<SshHost>,
+    pub app_config: AppConfig,
+    /// Keyring passwords by alias; empty unless they were included.
+    #[serde(default)]
+    pub passwords: BTreeMap<String, String>,
+}
+
+impl Drop for BackupBundle {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        for password in self.passwords.values_mut() {
+            password.zeroize();
+        }
+    }
+}
+
+/// What to do with a backed-up host whose alias already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    KeepExisting,
+    Replace,
+    /// Add the backed-up host under a new alias (`web` → `web-restored`).
+    KeepBoth,
+}
+
+/// Collects the hosts Rustmius manages (ssh_config, not inventories), the app
+/// config and, if asked, the stored passwords.
+pub async fn collect_backup(include_passwords: bool) -> anyhow::Result<BackupBundle> {
+    let hosts: Vec<SshHost> = load_hosts()?
+        .into_iter()
+        .filter(|host| host.inventory.is_none())
+        .collect();
+    let mut passwords = BTreeMap::new();
+    if include_passwords {
+        for host in &hosts {
+            if let Some(password) = get_keyring_password(&host.alias).await {
+                passwords.insert(host.alias.clone(), password);
+            }
+        }
+    }
+    Ok(BackupBundle {
+        created_at: chrono::Utc::now().timestamp(),
+        hosts,
+        app_config: exported_settings(load_app_config()?),
+        passwords,
+    })
+}
+
+/// Writes `bundle` to `path`, encrypted with `passphrase`.
+pub fn write_backup(path: &Path, bundle: &BackupBundle, passphrase: &str) -> anyhow::Result<()> {
+    let plaintext = Zeroizing::new(serde_json::to_vec(bundle)?);
+    let data = FileKey::new(passphrase)?.seal(MAGIC, &plaintext)?;
+    fs::write(path, data).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Reads and decrypts a backup written by [`write_backup`].
+pub fn read_backup(path: &Path, passphrase: &str) -> anyhow::Result<BackupBundle> {
+    let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let (_, plaintext) = FileKey::unseal(MAGIC, &data, passphrase)
+        .map_err(|e| anyhow::anyhow!("Could not decrypt backup: {}", e))?;
+    serde_json::from_slice(&plaintext).context("Backup contents are damaged")
+}
+
+/// How a restore will treat each backed-up host.
+#[derive(Debug, Default)]
+pub struct RestorePlan {
+    /// Hosts to add, already renamed where needed.
+    pub add: Vec<SshHost>,
+    /// (current, from backup) pairs to overwrite in place.
+    pub replace: Vec<(SshHost, SshHost)>,
+    pub skipped: Vec<String>,
+    /// Passwords by the alias the host will have after the restore.
+    pub passwords: Vec<(String, Zeroizing<String>)>,
+}
+
+/// Matches backed-up hosts against `existing` (case-insensitively, like the
+/// server dialog). Inventory hosts are never replaced.
+pub fn plan_restore(
+    bundle: &BackupBundle,
+    existing: &[SshHost],
+    policy: ConflictPolicy,
+) -> RestorePlan {
+    let mut plan = RestorePlan::default();
+    let mut taken: Vec<String> = existing.iter().map(|h| h.alias.to_lowercase()).collect();
+    for host in &bundle.hosts {
+        let current = existing
+            .iter()
+            .find(|h| h.alias.eq_ignore_ascii_case(&host.alias));
+        let restored_alias = match (current, policy) {
+            (None, _) => {
+                plan.add.push(host.clone());
+                host.alias.clone()
+            }
+            (Some(current), ConflictPolicy::Replace) if current.inventory.is_none() => {
+                let restored = SshHost {
+                    alias: current.alias.clone(),
+                    ..host.clone()
+                };
+                plan.replace.push((current.clone(), restored));
+                current.alias.clone()
+            }
+            (Some(_), ConflictPolicy::KeepBoth) => {
+                let alias = unique_alias(&host.alias, &taken);
+                plan.add.push(SshHost {
+                    alias: alias.clone(),
+                    ..host.clone()
+                });
+                alias
+            }
+            (Some(_), _) => {
+                plan.skipped.push(host.alias.clone());
+                continue;
+            }
+        };
+        taken.push(restored_alias.to_lowercase());
+        if let Some(password) = bundle.passwords.get(&host.alias) {
+            plan.passwords
+                .push((restored_alias, Zeroizing::new(password.clone())));
+        }
+    }
+    plan
+}
+
+fn unique_alias(alias: &str, taken: &[String]) -> String {
+    (1..)
+        .map(|n| match n {
+            1 => format!("{}-{}", alias, RESTORED_SUFFIX),
+            n => format!("{}-{}-{}", alias, RESTORED_SUFFIX, n),
+        })
+        .find(|candidate| !taken.contains(&candidate.to_lowercase()))
+        .unwrap_or_default()
+}
+
+/// Writes the planned hosts and, if asked, the backed-up settings. Passwords
+/// are left to the caller, which stores them in the keyring.
+pub fn apply_restore(
+    plan: &RestorePlan,
+    bundle: &BackupBundle,
+    restore_settings: bool,
+) -> anyhow::Result<()> {
+    if !plan.add.is_empty() {
+        add_hosts_to_config(&plan.add)?;
+    }
+    for (current, restored) in &plan.replace {
+        update_host_in_config(current, restored)?;
+    }
+    if restore_settings {
+        save_app_config(&restored_settings(&bundle.app_config, load_app_config()?))?;
+    }
+    Ok(())
+}
+
+/// The app config as backed up: the master password hash and the choice of
+/// password storage belong to this machine and stay out of the file.
+fn exported_settings(config: AppConfig) -> AppConfig {
+    AppConfig {
+        password_storage: Default::default(),
+        app_lock: Default::default(),
+        ..config
+    }
+}
+
+/// The backed-up settings, keeping the current app lock and password storage.
+fn restored_settings(backup: &AppConfig, current: AppConfig) -> AppConfig {
+    AppConfig {
+        password_storage: current.password_storage,
+        app_lock: current.app_lock,
+        ..backup.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_plan_resolves_conflicts() {
+        let host = |alias: &str, hostname: &str| SshHost {
+            alias: alias.to_string(),
+            hostname: hostname.to_string(),
+            ..Default::default()
+        };
+        let bundle = BackupBundle {
+            created_at: 0,
+            hosts: vec![host("web", "10.0.0.1"), host("db", "10.0.0.2")],
+            app_config: AppConfig::default(),
+            passwords: BTreeMap::from([("web".to_string(), "pw".to_string())]),
+        };
+        let existing = vec![host("WEB", "192.168.0.1"), host("web-restored", "x")];
+
+        let plan = plan_restore(&bundle, &existing, ConflictPolicy::KeepExisting);
+        assert_eq!(plan.add.len(), 1);
+        assert_eq!(plan.skipped, vec!["web"]);
+        assert!(plan.passwords.is_empty());
+
+        let plan = plan_restore(&bundle, &existing, ConflictPolicy::Replace);
+        assert_eq!(plan.replace[0].1.alias, "WEB");
+        assert_eq!(plan.replace[0].1.hostname, "10.0.0.1");
+        assert_eq!(plan.passwords[0].0, "WEB");
+
+        let plan = plan_restore(&bundle, &existing, ConflictPolicy::KeepBoth);
+        assert_eq!(plan.add[0].alias, "web-restored-2");
+        assert_eq!(plan.passwords[0].0, "web-restored-2");
+    }
+
+    #[test]
+    fn test_settings_keep_app_lock_and_password_storage_local() {
+        use crate::config_observer::{AppLockConfig, PasswordStorage};
+        let local = AppConfig {
+            password_storage: PasswordStorage::Vault,
+            app_lock: AppLockConfig {
+                password_hash: Some("$argon2id$local".to_string()),
+                idle_minutes: 5,
+            },
+            ..AppConfig::default()
+        };
+        let exported = exported_settings(local.clone());
+        assert_eq!(exported.app_lock, AppLockConfig::default());
+        assert_eq!(exported.password_storage, PasswordStorage::default());
+
+        let backup = AppConfig {
+            terminal_scrollback: 1234,
+            password_storage: PasswordStorage::SecretService,
+            app_lock: AppLockConfig {
+                password_hash: Some("$argon2id$other".to_string()),
+                idle_minutes: 0,
+            },
+            ..AppConfig::default()
+        };
+        let restored = restored_settings(&backup, local.clone());
+        assert_eq!(restored.terminal_scrollback, 1234);
+        assert_eq!(restored.app_lock, local.app_lock);
+        assert_eq!(restored.password_storage, PasswordStorage::Vault);
+    }
+}
@@ -0,0 +1,448 @@
+use crate::config_observer::{PasswordStorage, expand_tilde, load_app_config};
+use crate::crypto::FileKey;
+use anyhow::Context;
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+/// File signature of the password vault.
+const VAULT_MAGIC: &[u8; 8] = b"RMSVAUL1";
+
+static VAULT: RwLock<Option<UnlockedVault>> = RwLock::new(None);
+static SECRET_SERVICE_AVAILABLE: tokio::sync::OnceCell<bool> = tokio::sync::OnceCell::const_new();
+static APP_LOCKED: OnceLock<tokio::sync::watch::Sender<bool>> = OnceLock::new();
+static ASKPASS_SOCKET: OnceLock<Option<PathBuf>> = OnceLock::new();
+/// Askpass tokens handed out this session: token → (alias, key path).
+static ASKPASS_GRANTS: Mutex<BTreeMap<String, (String, Option<String>)>> =
+    Mutex::new(BTreeMap::new());
+
+/// What a stored secret belongs to.
+#[derive(Debug, Clone, Copy)]
+pub enum CredentialKey<'a> {
+    /// A server password, by alias.
+    Server(&'a str),
+    /// The passphrase of a private key, by path.
+    KeyPassphrase(&'a str),
+}
+
+impl CredentialKey<'_> {
+    /// The lookup attribute: the lower-cased alias, or the tilde-expanded key
+    /// path so every host using the same key shares one stored passphrase.
+    fn attribute(&self) -> (&'static str, String) {
+        match self {
+            Self::Server(alias) => ("rustmius-server-alias", alias.to_lowercase()),
+            Self::KeyPassphrase(path) => (
+                "rustmius-key-path",
+                expand_tilde(path).to_string_lossy().into_owned(),
+            ),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Self::Server(alias) => format!("Rustmius: SSH Password for {}", alias),
+            Self::KeyPassphrase(_) => {
+                format!("Rustmius: SSH Key Passphrase for {}", self.attribute().1)
+            }
+        }
+    }
+
+    fn vault_key(&self) -> String {
+        let (name, value) = self.attribute();
+        format!("{}={}", name, value)
+    }
+}
+
+/// A place server passwords and key passphrases are kept.
+#[async_trait::async_trait]
+pub trait CredentialStore: Send + Sync {
+    async fn get(&self, key: CredentialKey<'_>) -> Option<String>;
+    /// Stores `secret`, replacing any existing entry.
+    async fn store(&self, key: CredentialKey<'_>, secret: &str) -> anyhow::Result<()>;
+    async fn delete(&self, key: CredentialKey<'_>) -> anyhow::Result<()>;
+}
+
+/// The desktop keyring, through the Secret Service API. `oo7` zeroizes the
+/// secrets it holds; callers wipe their own plaintext copies.
+pub struct SecretServiceStore;
+
+#[async_trait::async_trait]
+impl CredentialStore for SecretServiceStore {
+    async fn get(&self, key: CredentialKey<'_>) -> Option<String> {
+        let keyring = oo7::Keyring::new().await.ok()?;
+        let (name, value) = key.attribute();
+        let items = keyring.search_items(&[(name, value.as_str())]).await.ok()?;
+        let item = items.first()?;
+        let secret = item.secret().await.ok()?;
+        std::str::from_utf8(&secret).map(String::from).ok()
+    }
+
+    async fn store(&self, key: CredentialKey<'_>, secret: &str) -> anyhow::Result<()> {
+        let keyring = oo7::Keyring::new().await?;
+        let (name, value) = key.attribute();
+        keyring
+            .create_item(
+                &key.label(),
+                &[(name, value.as_str())],
+                secret.as_bytes(),
+                true,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: CredentialKey<'_>) -> anyhow::Result<()> {
+        let keyring = oo7::Keyring::new().await?;
+        let (name, value) = key.attribute();
+        for item in keyring.search_items(&[(name, value.as_str())]).await? {
+            let _ = item.delete().await;
+        }
+        Ok(())
+    }
+}
+
+/// A local file encrypted with a master password, for systems without a
+/// Secret Service. It is unlocked once per session with [`unlock_vault`].
+pub struct VaultStore;
+
+#[async_trait::async_trait]
+impl CredentialStore for VaultStore {
+    async fn get(&self, key: CredentialKey<'_>) -> Option<String> {
+        vault_lookup(key).map(|secret| secret.to_string())
+    }
+
+    async fn store(&self, key: CredentialKey<'_>, secret: &str) -> anyhow::Result<()> {
+        with_unlocked_vault(|vault| {
+            vault
+                .entries
+                .insert(key.vault_key(), Zeroizing::new(secret.to_string()));
+            vault.save()
+        })
+    }
+
+    async fn delete(&self, key: CredentialKey<'_>) -> anyhow::Result<()> {
+        with_unlocked_vault(|vault| match vault.entries.remove(&key.vault_key()) {
+            Some(_) => vault.save(),
+            None => Ok(()),
+        })
+    }
+}
+
+/// The store selected by the `password_storage` setting. Waits while the app
+/// is locked.
+pub async fn active_store() -> &'static dyn CredentialStore {
+    wait_until_unlocked().await;
+    match active_storage().await {
+        PasswordStorage::Vault => &VaultStore,
+        _ => &SecretServiceStore,
+    }
+}
+
+/// Resolves [`PasswordStorage::Auto`] by checking, once per process, whether a
+/// Secret Service answers.
+pub async fn active_storage() -> PasswordStorage {
+    let configured = load_app_config()
+        .map(|config| config.password_storage)
+        .unwrap_or_default();
+    match configured {
+        PasswordStorage::Auto => {
+            // Boxed: inlining oo7's connection future overflows the layout depth
+            // of the UI futures that end up awaiting this.
+            let available = *SECRET_SERVICE_AVAILABLE
+                .get_or_init(|| Box::pin(async { oo7::Keyring::new().await.is_ok() }))
+                .await;
+            if available {
+                PasswordStorage::SecretService
+            } else {
+                PasswordStorage::Vault
+            }
+        }
+        storage => storage,
+    }
+}
+
+struct UnlockedVault {
+    path: PathBuf,
+    key: FileKey,
+    entries: BTreeMap<String, Zeroizing<String>>,
+}
+
+impl UnlockedVault {
+    /// Decrypts the vault at `path`, or creates an empty one there.
+    fn open(path: PathBuf, master_password: &str) -> anyhow::Result<Self> {
+        if !path.exists() {
+            let vault = Self {
+                path,
+                key: FileKey::new(master_password)?,
+                entries: BTreeMap::new(),
+            };
+            vault.save()?;
+            return Ok(vault);
+        }
+        let data = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let (key, plaintext) = FileKey::unseal(VAULT_MAGIC, &data, master_password)
+            .map_err(|e| anyhow::anyhow!("Could not unlock the vault: {}", e))?;
+        let entries: BTreeMap<String, String> =
+            serde_json::from_slice(&plaintext).context("Vault contents are damaged")?;
+        Ok(Self {
+            path,
+            key,
+            entries: entries
+                .into_iter()
+                .map(|(name, secret)| (name, Zeroizing::new(secret)))
+                .collect(),
+        })
+    }
+
+    /// Re-encrypts the entries under a fresh nonce and replaces the file.
+    fn save(&self) -> anyhow::Result<()> {
+        let entries: BTreeMap<&str, &str> = self
+            .entries
+            .iter()
+            .map(|(name, secret)| (name.as_str(), secret.as_str()))
+            .collect();
+        let plaintext = Zeroizing::new(serde_json::to_vec(&entries)?);
+        let data = self.key.seal(VAULT_MAGIC, &plaintext)?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context("Failed to create vault directory")?;
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp_path)
+            .context("Failed to write vault")?;
+        file.write_all(&data).context("Failed to write vault")?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path).context("Failed to replace vault")
+    }
+}
+
+fn app_lock_state() -> &'static tokio::sync::watch::Sender<bool> {
+    APP_LOCKED.get_or_init(|| tokio::sync::watch::Sender::new(false))
+}
+
+/// Locks or unlocks the app. While locked, secrets are only handed out once it
+/// is unlocked again.
+pub fn set_app_locked(locked: bool) {
+    app_lock_state().send_replace(locked);
+}
+
+pub fn is_app_locked() -> bool {
+    *app_lock_state().borrow()
+}
+
+async fn wait_until_unlocked() {
+    let mut state = app_lock_state().subscribe();
+    let _ = state.wait_for(|locked| !*locked).await;
+}
+
+/// PHC string (Argon2id) of the app lock's master password.
+pub fn hash_master_password(password: &str) -> anyhow::Result<String> {
+    use argon2::password_hash::{PasswordHasher, SaltString};
+    let salt = SaltString::generate(&mut OsRng);
+    argon2::Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to hash master password: {}", e))
+}
+
+pub fn verify_master_password(password: &str, hash: &str) -> bool {
+    use argon2::password_hash::{PasswordHash, PasswordVerifier};
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        argon2::Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+pub fn get_vault_path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("org", "rustmius", "Rustmius")
+        .map(|dirs| dirs.data_dir().join("vault.rmvault"))
+}
+
+pub fn vault_exists() -> bool {
+    get_vault_path().is_some_and(|path| path.exists())
+}
+
+pub fn is_vault_unlocked() -> bool {
+    VAULT.read().is_ok_and(|vault| vault.is_some())
+}
+
+/// Unlocks the vault for the rest of the session, creating it with
+/// `master_password` if there is none yet. Slow on purpose (Argon2); call it
+/// off the main thread.
+pub fn unlock_vault(master_password: &str) -> anyhow::Result<()> {
+    let path = get_vault_path().ok_or_else(|| anyhow::anyhow!("Could not determine vault path"))?;
+    let vault = UnlockedVault::open(path, master_password)?;
+    *VAULT
+        .write()
+        .map_err(|_| anyhow::anyhow!("Vault lock poisoned"))? = Some(vault);
+    Ok(())
+}
+
+fn vault_lookup(key: CredentialKey<'_>) -> Option<Zeroizing<String>> {
+    let vault = VAULT.read().ok()?;
+    vault.as_ref()?.entries.get(&key.vault_key()).cloned()
+}
+
+fn with_unlocked_vault<T>(
+    f: impl FnOnce(&mut UnlockedVault) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let mut guard = VAULT
+        .write()
+        .map_err(|_| anyhow::anyhow!("Vault lock poisoned"))?;
+    let vault = guard
+        .as_mut()
+        .ok_or_else(|| anyhow::anyhow!("The password vault is locked"))?;
+    f(vault)
+}
+
+/// Lets the `SSH_ASKPASS` helper, a separate process that can't unlock the
+/// vault, fetch `alias`'s secrets from this one. Returns the socket path and
+/// a token bound to that host, or `None` when the vault isn't in use.
+pub fn askpass_grant(alias: &str, key_path: Option<&str>) -> Option<(PathBuf, String)> {
+    if !is_vault_unlocked() {
+        return None;
+    }
+    let socket = ASKPASS_SOCKET.get_or_init(start_askpass_server).clone()?;
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    ASKPASS_GRANTS.lock().ok()?.insert(
+        token.clone(),
+        (alias.to_string(), key_path.map(str::to_string)),
+    );
+    Some((socket, token))
+}
+
+/// Removes the askpass socket, if one was opened.
+pub fn stop_askpass_server() {
+    if let Some(Some(socket)) = ASKPASS_SOCKET.get() {
+        let _ = fs::remove_file(socket);
+    }
+}
+
+/// Listens in the user's private runtime directory; the socket is only
+/// opened there, never in a shared temporary directory.
+fn start_askpass_server() -> Option<PathBuf> {
+    let runtime_dir = directories::BaseDirs::new()?.runtime_dir()?.to_path_buf();
+    let path = runtime_dir.join(format!("rustmius-askpass-{}.sock", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::warn!("Failed to open askpass socket {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o600));
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = answer_askpass(stream) {
+                tracing::debug!("Askpass request failed: {}", e);
+            }
+        }
+    });
+    Some(path)
+}
+
+/// Request: the token and `password` or `passphrase`, one per line. The reply
+/// is the secret, or nothing.
+fn answer_askpass(mut stream: UnixStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request = String::new();
+    (&stream).take(256).read_to_string(&mut request)?;
+    let mut lines = request.lines();
+    let (Some(token), kind) = (lines.next(), lines.next()) else {
+        return Ok(());
+    };
+    if is_app_locked() {
+        return Ok(());
+    }
+    let Some((alias, key_path)) = ASKPASS_GRANTS
+        .lock()
+        .ok()
+        .and_then(|grants| grants.get(token).cloned())
+    else {
+        return Ok(());
+    };
+    let secret = match (kind, key_path.as_deref()) {
+        (Some("passphrase"), Some(key_path)) => {
+            vault_lookup(CredentialKey::KeyPassphrase(key_path))
+        }
+        (Some("password"), _) => vault_lookup(CredentialKey::Server(&alias)),
+        _ => None,
+    };
+    if let Some(secret) = secret {
+        stream.write_all(secret.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Asks the app that spawned this askpass helper for a secret.
+pub fn askpass_request(socket: &Path, token: &str, passphrase: bool) -> Option<Zeroizing<String>> {
+    let mut stream = UnixStream::connect(socket).ok()?;
+    stream.set_read_timeout(Some(Duration::from_secs(5))).ok()?;
+    let kind = if passphrase { "passphrase" } else { "password" };
+    stream
+        .write_all(format!("{}\n{}\n", token, kind).as_bytes())
+        .ok()?;
+    stream.shutdown(std::net::Shutdown::Write).ok()?;
+    let mut secret = Zeroizing::new(String::new());
+    stream.read_to_string(&mut secret).ok()?;
+    Some(secret).filter(|secret| !secret.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_file_round_trips_and_needs_the_master_password() {
+        let path = std::env::temp_dir().join(format!(
+            "rustmius-vault-test-{}.rmvault",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let mut vault = UnlockedVault::open(path.clone(), "master").unwrap();
+        let key = CredentialKey::Server("Web");
+        vault
+            .entries
+            .insert(key.vault_key(), Zeroizing::new("hunter2".to_string()));
+        vault.save().unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        let reopened = UnlockedVault::open(path.clone(), "master").unwrap();
+        let stored = reopened
+            .entries
+            .get(&CredentialKey::Server("web").vault_key())
+            .unwrap();
+        assert_eq!(stored.as_str(), "hunter2");
+        assert!(UnlockedVault::open(path.clone(), "wrong").is_err());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_master_password_hash_verifies_only_the_same_password() {
+        let hash = hash_master_password("open sesame").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_master_password("open sesame", &hash));
+        assert!(!verify_master_password("open sesame!", &hash));
+        assert!(!verify_master_password("open sesame", "not a hash"));
+    }
+}
@@ -0,0 +1,154 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload, rand_core::RngCore};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use zeroize::Zeroizing;
+
+pub const MAGIC_LEN: usize = 8;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// Magic, Argon2 memory (KiB), iterations and parallelism, salt, nonce.
+const HEADER_LEN: usize = MAGIC_LEN + 12 + SALT_LEN + NONCE_LEN;
+/// Ceilings on the Argon2 costs read from a header, so a damaged or hostile
+/// file can't make key derivation allocate or spin without bound.
+const MAX_M_COST_KIB: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 10;
+const MAX_P_COST: u32 = 16;
+
+/// An Argon2id-derived ChaCha20-Poly1305 key for the passphrase-protected
+/// files (backups, the password vault). Files carry the KDF parameters and
+/// salt in their header, which is also the associated data.
+pub struct FileKey {
+    key: Zeroizing<[u8; 32]>,
+    params: Params,
+    salt: [u8; SALT_LEN],
+}
+
+impl FileKey {
+    /// Derives a key from `passphrase` with a fresh salt.
+    pub fn new(passphrase: &str) -> anyhow::Result<Self> {
+        let params = Params::default();
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_key(passphrase, &salt, params.clone())?;
+        Ok(Self { key, params, salt })
+    }
+
+    /// Encrypts `plaintext` under a fresh nonce.
+    pub fn seal(&self, magic: &[u8; MAGIC_LEN], plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut data = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+        data.extend_from_slice(magic);
+        data.extend_from_slice(&self.params.m_cost().to_be_bytes());
+        data.extend_from_slice(&self.params.t_cost().to_be_bytes());
+        data.extend_from_slice(&self.params.p_cost().to_be_bytes());
+        data.extend_from_slice(&self.salt);
+        data.extend_from_slice(&nonce);
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(self.key.as_ref()));
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: &data,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+        data.extend_from_slice(&ciphertext);
+        Ok(data)
+    }
+
+    /// Decrypts `data`, returning the key it was sealed with so the caller can
+    /// write it back without asking for the passphrase again.
+    pub fn unseal(
+        magic: &[u8; MAGIC_LEN],
+        data: &[u8],
+        passphrase: &str,
+    ) -> anyhow::Result<(Self, Zeroizing<Vec<u8>>)> {
+        if data.len() < HEADER_LEN || !data.starts_with(magic) {
+            anyhow::bail!("Unrecognised file format");
+        }
+        let (header, ciphertext) = data.split_at(HEADER_LEN);
+        let u32_at = |offset: usize| {
+            u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap_or_default())
+        };
+        let m = MAGIC_LEN;
+        let (m_cost, t_cost, p_cost) = (u32_at(m), u32_at(m + 4), u32_at(m + 8));
+        if m_cost > MAX_M_COST_KIB || t_cost > MAX_T_COST || p_cost > MAX_P_COST {
+            anyhow::bail!(
+                "Invalid file header: key derivation costs too high (m={}, t={}, p={})",
+                m_cost,
+                t_cost,
+                p_cost
+            );
+        }
+        let params = Params::new(m_cost, t_cost, p_cost, None)
+            .map_err(|e| anyhow::anyhow!("Invalid file header: {}", e))?;
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&header[m + 12..m + 12 + SALT_LEN]);
+        let nonce = Nonce::from_slice(&header[m + 12 + SALT_LEN..]);
+
+        let key = derive_key(passphrase, &salt, params.clone())?;
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(key.as_ref()));
+        let plaintext = cipher
+            .decrypt(
+                nonce,
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| anyhow::anyhow!("Wrong passphrase or damaged file"))?;
+        Ok((Self { key, params, salt }, plaintext))
+    }
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    params: Params,
+) -> anyhow::Result<Zeroizing<[u8; 32]>> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealed_data_round_trips_and_rejects_wrong_passphrase() {
+        const MAGIC: &[u8; MAGIC_LEN] = b"TESTMAG1";
+        let key = FileKey::new("correct horse").unwrap();
+        let data = key.seal(MAGIC, b"{\"hosts\":[]}").unwrap();
+        assert!(data.starts_with(MAGIC));
+        let (reopened, plaintext) = FileKey::unseal(MAGIC, &data, "correct horse").unwrap();
+        assert_eq!(&*plaintext, b"{\"hosts\":[]}");
+        assert!(FileKey::unseal(MAGIC, &data, "battery staple").is_err());
+        assert!(FileKey::unseal(b"OTHERMAG", &data, "correct horse").is_err());
+
+        let resealed = reopened.seal(MAGIC, b"again").unwrap();
+        let (_, plaintext) = FileKey::unseal(MAGIC, &resealed, "correct horse").unwrap();
+        assert_eq!(&*plaintext, b"again");
+
+        let mut tampered = data.clone();
+        tampered[MAGIC_LEN + 12] ^= 1;
+        assert!(FileKey::unseal(MAGIC, &tampered, "correct horse").is_err());
+    }
+
+    #[test]
+    fn test_unseal_rejects_inflated_kdf_costs() {
+        const MAGIC: &[u8; MAGIC_LEN] = b"TESTMAG1";
+        let data = FileKey::new("pw").unwrap().seal(MAGIC, b"x").unwrap();
+        for offset in [MAGIC_LEN, MAGIC_LEN + 4, MAGIC_LEN + 8] {
+            let mut inflated = data.clone();
+            inflated[offset..offset + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+            let err = FileKey::unseal(MAGIC, &inflated, "pw").err().unwrap();
+            assert!(err.to_string().contains("too high"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use ssh2::{KeyboardInteractivePrompt, Prompt};
+use std::sync::OnceLock;
+use tokio::sync::oneshot;
+use tracing::warn;
+
+/// A single question of a keyboard-interactive challenge.
+#[derive(Debug, Clone)]
+pub struct ChallengePrompt {
+    pub text: String,
+    /// Whether the answer may be shown while typing (`false` for passwords and codes).
+    pub echo: bool,
+}
+
+/// Question handed to the UI while authenticating.
+#[derive(Debug, Clone)]
+pub enum AuthPrompt {
+    /// A keyboard-interactive round (PAM password, TOTP, Duo…). Servers may send
+    /// several rounds, and each round may contain several prompts.
+    KeyboardInteractive {
+        alias: String,
+        username: String,
+        instructions: String,
+        prompts: Vec<ChallengePrompt>,
+    },
+    /// The identity file is encrypted; `retry` is set after a wrong passphrase.
+    Passphrase {
+        alias: String,
+        key_path: String,
+        retry: bool,
+    },
+}
+
+/// The user's reply: one answer per prompt (a single one for a passphrase).
+#[derive(Debug, Clone, Default)]
+pub struct AuthAnswer {
+    pub answers: Vec<String>,
+    /// The user asked for the secret to be saved in the keyring.
+    pub remember: bool,
+}
+
+type Prompter = Box<dyn Fn(AuthPrompt, oneshot::Sender<Option<AuthAnswer>>) + Send + Sync>;
+
+static PROMPTER: OnceLock<Prompter> = OnceLock::new();
+
+/// Installs the callback used to ask the user for authentication secrets.
+/// Answering `None` or dropping the sender counts as a cancellation.
+pub fn set_auth_prompter(
+    prompter: impl Fn(AuthPrompt, oneshot::Sender<Option<AuthAnswer>>) + Send + Sync + 'static,
+) {
+    let _ = PROMPTER.set(Box::new(prompter));
+}
+
+/// Asks the installed prompter and blocks until it answers. Must be called from a
+/// blocking task, never from the GTK main loop.
+fn ask_user_blocking(prompt: AuthPrompt) -> Option<AuthAnswer> {
+    let Some(prompter) = PROMPTER.get() else {
+        warn!("No authentication prompter installed");
+        return None;
+    };
+    let (tx, rx) = oneshot::channel();
+    prompter(prompt, tx);
+    rx.blocking_recv().ok().flatten()
+}
+
+/// Answers libssh2's keyboard-interactive callback. A stored password fills the
+/// first password-looking prompt; everything else (OTP codes, push confirmations)
+/// goes to the user.
+pub struct InteractivePrompter {
+    alias: String,
+    password: Option<zeroize::Zeroizing<String>>,
+    /// Set when the user cancels, so the caller can tell it apart from a wrong answer.
+    pub cancelled: bool,
+}
+
+impl InteractivePrompter {
+    pub fn new(alias: &str, password: Option<zeroize::Zeroizing<String>>) -> Self {
+        Self {
+            alias: alias.to_string(),
+            password,
+            cancelled: false,
+        }
+    }
+}
+
+impl KeyboardInteractivePrompt for InteractivePrompter {
+    fn prompt<'a>(
+        &mut self,
+        username: &str,
+        instructions: &str,
+        prompts: &[Prompt<'a>],
+    ) -> Vec<String> {
+        if prompts.is_empty() || self.cancelled {
+            return prompts.iter().map(|_| String::new()).collect();
+        }
+
+        if prompts.len() == 1
+            && !prompts[0].echo
+            && prompts[0].text.to_lowercase().contains("password")
+            && let Some(password) = self.password.take()
+        {
+            tracing::trace!("Answering keyboard-interactive password prompt");
+            return vec![password.to_string()];
+        }
+
+        let prompt = AuthPrompt::KeyboardInteractive {
+            alias: self.alias.clone(),
+            username: username.to_string(),
+            instructions: instructions.to_string(),
+            prompts: prompts
+                .iter()
+                .map(|p| ChallengePrompt {
+                    text: p.text.to_string(),
+                    echo: p.echo,
+                })
+                .collect(),
+        };
+        match ask_user_blocking(prompt) {
+            Some(answer) => answer.answers,
+            None => {
+                self.cancelled = true;
+                prompts.iter().map(|_| String::new()).collect()
+            }
+        }
+    }
+}
+
+/// Asks the user for the passphrase of an encrypted identity file. Returns the
+/// passphrase and whether to remember it, or `None` when the user cancelled.
+/// Blocking, like [`InteractivePrompter`].
+pub fn ask_passphrase_blocking(
+    alias: &str,
+    key_path: &str,
+    retry: bool,
+) -> Option<(zeroize::Zeroizing<String>, bool)> {
+    let answer = ask_user_blocking(AuthPrompt::Passphrase {
+        alias: alias.to_string(),
+        key_path: key_path.to_string(),
+        retry,
+    })?;
+    let passphrase = answer.answers.into_iter().next()?;
+    Some((zeroize::Zeroizing::new(passphrase), answer.remember))
+}
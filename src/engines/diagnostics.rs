@@ -0,0 +1,461 @@
+use crate::config_observer::{SshHost, load_app_config};
+use crate::engines::known_hosts::{HostKeyStatus, inspect_host_key, verify_host_key};
+use crate::engines::ssh::{
+    connect_timeout, handshake, open_direct_transport, open_jump_chain, try_authenticate,
+};
+use ssh2::{MethodType, Session};
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::{TcpStream, lookup_host};
+use tracing::{info, instrument};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepStatus {
+    Passed,
+    /// Something is off but the connection can still go on (an unreachable
+    /// address among several, an unknown host key, a refused method…).
+    Warning,
+    Failed,
+}
+
+/// One checked step of a connection attempt.
+#[derive(Debug, Clone)]
+pub struct DiagnosticStep {
+    pub title: String,
+    pub status: StepStatus,
+    pub detail: String,
+    pub elapsed: Option<Duration>,
+}
+
+/// Everything learned while connecting to a host, in order.
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticReport {
+    pub steps: Vec<DiagnosticStep>,
+}
+
+impl DiagnosticReport {
+    /// Whether the connection got through authentication.
+    pub fn succeeded(&self) -> bool {
+        !self.steps.is_empty() && self.steps.iter().all(|s| s.status != StepStatus::Failed)
+    }
+
+    fn push(
+        &mut self,
+        title: impl Into<String>,
+        status: StepStatus,
+        detail: impl Into<String>,
+        elapsed: Option<Duration>,
+    ) {
+        self.steps.push(DiagnosticStep {
+            title: title.into(),
+            status,
+            detail: detail.into(),
+            elapsed,
+        });
+    }
+}
+
+/// Plain-text rendering, used to copy the report.
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            let mark = match step.status {
+                StepStatus::Passed => "OK",
+                StepStatus::Warning => "WARN",
+                StepStatus::Failed => "FAIL",
+            };
+            write!(f, "[{}] {}", mark, step.title)?;
+            if let Some(elapsed) = step.elapsed {
+                write!(f, " ({} ms)", elapsed.as_millis())?;
+            }
+            writeln!(f)?;
+            for line in step.detail.lines() {
+                writeln!(f, "    {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Connects to the host step by step (DNS, TCP, handshake, host key,
+/// authentication) and reports on each one instead of stopping at the first
+/// error. The session is closed afterwards and never enters the pool.
+#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias))]
+pub async fn diagnose_connection(host: &SshHost, password: Option<&str>) -> DiagnosticReport {
+    info!("Running connection diagnostics for {}", host.alias);
+    let mut report = DiagnosticReport::default();
+    let Some(sess) = open_session(host, &mut report).await else {
+        return report;
+    };
+
+    report.push(
+        "Negotiated algorithms",
+        StepStatus::Passed,
+        describe_methods(&sess),
+        None,
+    );
+
+    if !check_host_key(&sess, host, &mut report).await {
+        return report;
+    }
+
+    let user = host.user.clone().unwrap_or_else(|| "root".to_string());
+    let methods_sess = sess.clone();
+    let offered =
+        tokio::task::spawn_blocking(move || methods_sess.auth_methods(&user).map(str::to_string))
+            .await;
+    match offered {
+        Ok(Ok(methods)) => report.push(
+            "Authentication methods offered",
+            StepStatus::Passed,
+            methods.replace(',', ", "),
+            None,
+        ),
+        Ok(Err(_)) if sess.authenticated() => report.push(
+            "Authentication methods offered",
+            StepStatus::Passed,
+            "The server accepts connections without credentials",
+            None,
+        ),
+        Ok(Err(e)) => report.push(
+            "Authentication methods offered",
+            StepStatus::Warning,
+            e.to_string(),
+            None,
+        ),
+        Err(e) => report.push(
+            "Authentication methods offered",
+            StepStatus::Warning,
+            e.to_string(),
+            None,
+        ),
+    }
+
+    let started = Instant::now();
+    let (result, attempts) = try_authenticate(sess.clone(), host, password).await;
+    let elapsed = started.elapsed();
+    let authenticated = result.is_ok();
+    for attempt in &attempts {
+        let (status, detail) = match &attempt.error {
+            None => (StepStatus::Passed, "Accepted".to_string()),
+            // A refused method is expected when a later one gets in.
+            Some(error) if authenticated => (StepStatus::Warning, error.clone()),
+            Some(error) => (StepStatus::Failed, error.clone()),
+        };
+        report.push(
+            format!("Authentication: {}", attempt.method),
+            status,
+            detail,
+            None,
+        );
+    }
+    match result {
+        Ok(_) => report.push(
+            "Authenticated",
+            StepStatus::Passed,
+            format!("Logged in as {}", host.user.as_deref().unwrap_or("root")),
+            Some(elapsed),
+        ),
+        Err(e) => report.push(
+            "Authentication failed",
+            StepStatus::Failed,
+            format!("{:#}", e),
+            Some(elapsed),
+        ),
+    }
+
+    let _ =
+        tokio::task::spawn_blocking(move || sess.disconnect(None, "Diagnostics done", None)).await;
+    report
+}
+
+/// Opens the transport and runs the handshake, reporting each step.
+async fn open_session(host: &SshHost, report: &mut DiagnosticReport) -> Option<Session> {
+    let proxied = host.proxy_command.is_some()
+        || load_app_config().is_ok_and(|config| config.outbound_proxy.is_enabled());
+    let (result, started) = if host.proxy_jump.is_empty() && proxied {
+        let started = Instant::now();
+        match open_direct_transport(host).await {
+            Ok(transport) => {
+                let detail = match &host.proxy_command {
+                    Some(command) => format!("ProxyCommand: {}", command),
+                    None => "Connected through the outbound proxy".to_string(),
+                };
+                report.push("Proxy", StepStatus::Passed, detail, Some(started.elapsed()));
+                let started = Instant::now();
+                (handshake(transport, host).await, started)
+            }
+            Err(e) => {
+                report.push(
+                    "Proxy",
+                    StepStatus::Failed,
+                    format!("{:#}", e),
+                    Some(started.elapsed()),
+                );
+                return None;
+            }
+        }
+    } else if host.proxy_jump.is_empty() {
+        let tcp = connect_direct(host, report).await?;
+        let started = Instant::now();
+        (handshake(tcp, host).await, started)
+    } else {
+        let started = Instant::now();
+        match open_jump_chain(host).await {
+            Ok(stream) => {
+                report.push(
+                    "Jump hosts",
+                    StepStatus::Passed,
+                    format!(
+                        "Reached {} through {}",
+                        host.hostname,
+                        host.proxy_jump.join(" → ")
+                    ),
+                    Some(started.elapsed()),
+                );
+                let started = Instant::now();
+                (handshake(stream, host).await, started)
+            }
+            Err(e) => {
+                report.push(
+                    "Jump hosts",
+                    StepStatus::Failed,
+                    format!("{:#}", e),
+                    Some(started.elapsed()),
+                );
+                return None;
+            }
+        }
+    };
+
+    match result {
+        Ok(sess) => {
+            report.push(
+                "SSH handshake",
+                StepStatus::Passed,
+                format!("Server: {}", sess.banner().unwrap_or("(no banner)")),
+                Some(started.elapsed()),
+            );
+            Some(sess)
+        }
+        Err(e) => {
+            report.push(
+                "SSH handshake",
+                StepStatus::Failed,
+                format!("{:#}", e),
+                Some(started.elapsed()),
+            );
+            None
+        }
+    }
+}
+
+/// Resolves the host and tries every address, keeping the first connection.
+async fn connect_direct(
+    host: &SshHost,
+    report: &mut DiagnosticReport,
+) -> Option<std::net::TcpStream> {
+    let port = host.port.unwrap_or(22);
+    let started = Instant::now();
+    let addrs: Vec<SocketAddr> = match lookup_host((host.hostname.as_str(), port)).await {
+        Ok(addrs) => addrs.collect(),
+        Err(e) => {
+            report.push(
+                "DNS lookup",
+                StepStatus::Failed,
+                format!("Could not resolve {}: {}", host.hostname, e),
+                Some(started.elapsed()),
+            );
+            return None;
+        }
+    };
+    let mut resolved = addrs
+        .iter()
+        .map(|addr| addr.ip().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (addrs, skipped): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs
+        .into_iter()
+        .partition(|addr| host.address_family.is_none_or(|family| family.allows(addr)));
+    if !skipped.is_empty() {
+        resolved.push_str(&format!(
+            "\n{} address(es) skipped by AddressFamily",
+            skipped.len()
+        ));
+    }
+    report.push(
+        "DNS lookup",
+        if addrs.is_empty() {
+            StepStatus::Failed
+        } else {
+            StepStatus::Passed
+        },
+        resolved,
+        Some(started.elapsed()),
+    );
+    if addrs.is_empty() {
+        return None;
+    }
+
+    let timeout = connect_timeout(host);
+    let mut connected = None;
+    for addr in &addrs {
+        let started = Instant::now();
+        let title = format!("TCP connect to {}", addr);
+        match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+            Ok(Ok(stream)) => {
+                report.push(
+                    title,
+                    StepStatus::Passed,
+                    "Connected",
+                    Some(started.elapsed()),
+                );
+                connected.get_or_insert(stream);
+            }
+            Ok(Err(e)) => report.push(
+                title,
+                StepStatus::Warning,
+                e.to_string(),
+                Some(started.elapsed()),
+            ),
+            Err(_) => report.push(
+                title,
+                StepStatus::Warning,
+                format!("No answer within {}s", timeout.as_secs()),
+                Some(started.elapsed()),
+            ),
+        }
+    }
+
+    let Some(tcp) = connected else {
+        report.push(
+            "TCP connect",
+            StepStatus::Failed,
+            format!(
+                "No address of {} accepted a connection on port {}",
+                host.hostname, port
+            ),
+            None,
+        );
+        return None;
+    };
+    match tcp.into_std().and_then(|tcp| {
+        tcp.set_nonblocking(false)?;
+        Ok(tcp)
+    }) {
+        Ok(tcp) => Some(tcp),
+        Err(e) => {
+            report.push("TCP connect", StepStatus::Failed, e.to_string(), None);
+            None
+        }
+    }
+}
+
+/// Formats the algorithms agreed on during the handshake.
+fn describe_methods(sess: &Session) -> String {
+    let method = |kind| sess.methods(kind).unwrap_or("unknown");
+    let both_ways = |out, inbound| {
+        let (out, inbound) = (method(out), method(inbound));
+        if out == inbound {
+            out.to_string()
+        } else {
+            format!("{} (out) / {} (in)", out, inbound)
+        }
+    };
+    format!(
+        "Key exchange: {}\nHost key: {}\nCipher: {}\nMAC: {}\nCompression: {}",
+        method(MethodType::Kex),
+        method(MethodType::HostKey),
+        both_ways(MethodType::CryptCs, MethodType::CryptSc),
+        both_ways(MethodType::MacCs, MethodType::MacSc),
+        both_ways(MethodType::CompCs, MethodType::CompSc),
+    )
+}
+
+/// Reports the host key. Unknown keys go through the usual trust prompt; a
+/// changed key stops the test before any credential is sent.
+async fn check_host_key(sess: &Session, host: &SshHost, report: &mut DiagnosticReport) -> bool {
+    let (status, info) = match inspect_host_key(sess, host).await {
+        Ok(inspected) => inspected,
+        Err(e) => {
+            report.push("Host key", StepStatus::Failed, format!("{:#}", e), None);
+            return false;
+        }
+    };
+    let key = format!("{} {}", info.key_type, info.fingerprint);
+    match status {
+        HostKeyStatus::Trusted => {
+            report.push(
+                "Host key",
+                StepStatus::Passed,
+                format!("{}\nMatches known_hosts", key),
+                None,
+            );
+            true
+        }
+        HostKeyStatus::Unknown => match verify_host_key(sess, host).await {
+            Ok(()) => {
+                report.push(
+                    "Host key",
+                    StepStatus::Warning,
+                    format!("{}\nWas not in known_hosts; added after confirmation", key),
+                    None,
+                );
+                true
+            }
+            Err(e) => {
+                report.push(
+                    "Host key",
+                    StepStatus::Failed,
+                    format!("{}\n{:#}", key, e),
+                    None,
+                );
+                false
+            }
+        },
+        HostKeyStatus::Changed => {
+            report.push(
+                "Host key",
+                StepStatus::Failed,
+                format!(
+                    "{}\nDoes NOT match known_hosts. Authentication was not attempted.",
+                    key
+                ),
+                None,
+            );
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_renders_steps_and_fails_on_any_failed_step() {
+        let mut report = DiagnosticReport::default();
+        report.push(
+            "DNS lookup",
+            StepStatus::Passed,
+            "192.0.2.1",
+            Some(Duration::from_millis(12)),
+        );
+        report.push(
+            "Authentication: agent",
+            StepStatus::Warning,
+            "No agent",
+            None,
+        );
+        assert!(report.succeeded());
+        report.push("Authenticated", StepStatus::Failed, "Denied\nTwice", None);
+        assert!(!report.succeeded());
+        assert_eq!(
+            report.to_string(),
+            "[OK] DNS lookup (12 ms)\n    192.0.2.1\n\
+             [WARN] Authentication: agent\n    No agent\n\
+             [FAIL] Authenticated\n    Denied\n    Twice\n"
+        );
+    }
+}
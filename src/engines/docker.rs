@@ -1,5 +1,8 @@
 use crate::config_observer::SshHost;
+use crate::engines::exec::{CommandStream, exec_command_once, stream_command};
 use crate::engines::ssh::run_remote_command;
+use anyhow::Context;
+use std::time::Duration;
 use tracing::{info, instrument};
 
 /// Shell prologue that picks the right Docker invocation (`docker` if the socket
@@ -7,6 +10,12 @@ use tracing::{info, instrument};
 /// Centralized so the detection logic stays in one place.
 const DOCKER_BIN: &str = "DOCKER_BIN=$(if [ -w /var/run/docker.sock ]; then echo 'docker'; else echo 'sudo -n docker'; fi); ";
 
+/// Longest a Docker action (start, stop, rm…) may take before it is reported as failed.
+const ACTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Number of past log lines shown before following new ones.
+const LOG_TAIL_LINES: u32 = 200;
+
 /// Retrieves Docker statistics (containers, images, running, paused) from the remote host.
 #[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias))]
 pub async fn get_docker_stats(
@@ -69,6 +78,24 @@ pub async fn perform_docker_action(
     );
     let safe_name = item_name.replace('\'', "'\\''");
     let cmd = format!("{DOCKER_BIN}$DOCKER_BIN {action} '{safe_name}'");
-    run_remote_command(host, password, &cmd).await?;
+    exec_command_once(host, password, &cmd, Some(ACTION_TIMEOUT))
+        .await?
+        .check()
+        .with_context(|| format!("docker {} {} failed", action, item_name))?;
     Ok(())
 }
+
+/// Follows the logs of a container. The stream runs until it is cancelled or the
+/// container stops.
+#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias, container = %container))]
+pub async fn stream_container_logs(
+    host: &SshHost,
+    password: Option<&str>,
+    container: &str,
+) -> anyhow::Result<CommandStream> {
+    info!("Following logs of '{}' at {}", container, host.alias);
+    let safe_name = container.replace('\'', "'\\''");
+    let cmd =
+        format!("{DOCKER_BIN}$DOCKER_BIN logs --follow --tail {LOG_TAIL_LINES} '{safe_name}'");
+    stream_command(host, password, &cmd).await
+}
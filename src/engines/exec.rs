@@ -0,0 +1,474 @@
+use crate::config_observer::SshHost;
+use crate::engines::relay::{BUF_SIZE, IdleBackoff, would_block};
+use crate::engines::ssh::{
+    ExecSession, establish_exec_session, invalidate_exec_session, is_connection_error,
+    touch_exec_session, use_exec_session,
+};
+use anyhow::Context;
+use ssh2::Channel;
+use std::io::{ErrorKind, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, TryLockError};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, instrument, warn};
+
+/// Everything a finished remote command produced.
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_status: i32,
+    pub duration: Duration,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.exit_status == 0
+    }
+
+    /// Turns a non-zero exit status into an error carrying what the command printed.
+    pub fn check(self) -> anyhow::Result<Self> {
+        if self.success() {
+            return Ok(self);
+        }
+        let message = if self.stderr.trim().is_empty() {
+            self.stdout.trim()
+        } else {
+            self.stderr.trim()
+        };
+        if message.is_empty() {
+            anyhow::bail!("Command exited with status {}", self.exit_status);
+        }
+        anyhow::bail!(
+            "Command exited with status {}: {}",
+            self.exit_status,
+            message
+        );
+    }
+}
+
+/// A line of output of a streamed command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputLine {
+    Stdout(String),
+    Stderr(String),
+}
+
+/// How a streamed command ended.
+#[derive(Debug, Clone, Copy)]
+pub struct CommandExit {
+    pub exit_status: i32,
+    pub duration: Duration,
+}
+
+/// Stops a running command; cloning it gives another handle to the same command.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A command whose output is delivered line by line while it runs.
+pub struct CommandStream {
+    /// Closed once the command has finished (or failed, or was cancelled).
+    pub lines: mpsc::UnboundedReceiver<OutputLine>,
+    pub cancel: CancelHandle,
+    exit: oneshot::Receiver<anyhow::Result<CommandExit>>,
+}
+
+impl CommandStream {
+    /// Waits for the command to finish. Lines not yet received are discarded.
+    pub async fn wait(self) -> anyhow::Result<CommandExit> {
+        self.exit
+            .await
+            .map_err(|_| anyhow::anyhow!("Command runner stopped unexpectedly"))?
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Source {
+    Stdout,
+    Stderr,
+}
+
+/// Longest a channel may take to open and start its program.
+const SETUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// When a running command must give up.
+pub(crate) struct Limits {
+    cancel: CancelHandle,
+    timeout: Option<Duration>,
+    started: Instant,
+}
+
+impl Limits {
+    /// Limits for short setup steps (opening a channel, requesting a pty…).
+    pub(crate) fn setup() -> Self {
+        Self {
+            cancel: CancelHandle::default(),
+            timeout: Some(SETUP_TIMEOUT),
+            started: Instant::now(),
+        }
+    }
+
+    /// Limits for the setup steps of a command: cancelled along with it, and
+    /// bounded by [`SETUP_TIMEOUT`] even when the command itself may run forever.
+    fn setup_phase(&self) -> Self {
+        let remaining = self
+            .timeout
+            .map(|timeout| timeout.saturating_sub(self.started.elapsed()));
+        Self {
+            cancel: self.cancel.clone(),
+            timeout: Some(remaining.map_or(SETUP_TIMEOUT, |r| r.min(SETUP_TIMEOUT))),
+            started: Instant::now(),
+        }
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.cancel.is_cancelled() {
+            anyhow::bail!("Command cancelled");
+        }
+        if let Some(timeout) = self.timeout
+            && self.started.elapsed() >= timeout
+        {
+            anyhow::bail!("Command timed out after {}s", timeout.as_secs_f32());
+        }
+        Ok(())
+    }
+}
+
+/// Retries a non-blocking libssh2 call until it stops asking to be called again.
+pub(crate) fn retry<T>(
+    limits: &Limits,
+    mut op: impl FnMut() -> Result<T, ssh2::Error>,
+) -> anyhow::Result<T> {
+    let mut backoff = IdleBackoff::new();
+    loop {
+        match op() {
+            Err(e) if would_block(&e) => {
+                limits.check()?;
+                backoff.sleep();
+            }
+            result => return Ok(result?),
+        }
+    }
+}
+
+/// Opens a channel on the non-blocking execution session. Waiting for the
+/// session's channel-open turn counts against `limits` too.
+pub(crate) fn open_channel(sess: &ExecSession, limits: &Limits) -> anyhow::Result<Channel> {
+    let mut backoff = IdleBackoff::new();
+    let _open = loop {
+        match sess.channel_open.try_lock() {
+            Ok(guard) => break guard,
+            Err(TryLockError::Poisoned(e)) => break e.into_inner(),
+            Err(TryLockError::WouldBlock) => {
+                limits.check().context("Failed to open SSH channel")?;
+                backoff.sleep();
+            }
+        }
+    };
+    retry(limits, || sess.session.channel_session()).context("Failed to open SSH channel")
+}
+
+/// Runs `command` on the non-blocking session and hands every chunk of output to
+/// `sink`. Returns the exit status.
+fn drive_command(
+    sess: &ExecSession,
+    host: &SshHost,
+    command: &str,
+    limits: &Limits,
+    mut sink: impl FnMut(Source, &[u8]),
+) -> anyhow::Result<i32> {
+    let setup = limits.setup_phase();
+    let mut channel = open_channel(sess, &setup)?;
+    retry(&setup, || channel.exec(command))
+        .with_context(|| format!("Failed to execute command: {}", command))?;
+
+    let mut buf = vec![0u8; BUF_SIZE];
+    let mut backoff = IdleBackoff::new();
+    loop {
+        let mut progressed = false;
+        for source in [Source::Stdout, Source::Stderr] {
+            match read_source(&mut channel, source, &mut buf) {
+                Ok(0) => {}
+                Ok(n) => {
+                    sink(source, &buf[..n]);
+                    progressed = true;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e).context("Failed to read command output"),
+            }
+        }
+        if !progressed && channel.eof() {
+            break;
+        }
+        if let Err(e) = limits.check() {
+            // Closing the channel makes the server hang up on the command.
+            let _ = channel.close();
+            return Err(e);
+        }
+        if progressed {
+            touch_exec_session(host);
+            backoff.reset();
+        } else {
+            backoff.sleep();
+        }
+    }
+
+    retry(limits, || channel.close())?;
+    retry(limits, || channel.wait_close())?;
+    Ok(channel.exit_status()?)
+}
+
+fn read_source(channel: &mut Channel, source: Source, buf: &mut [u8]) -> std::io::Result<usize> {
+    match source {
+        Source::Stdout => channel.read(buf),
+        Source::Stderr => channel.stderr().read(buf),
+    }
+}
+
+/// Runs a command on the remote host and collects its output and exit status.
+/// With a `timeout`, the command is abandoned (and the channel closed) once it
+/// runs longer. A non-zero exit status is not an error; see [`CommandOutput::check`].
+/// After a dropped connection the command is run again, so this is for commands
+/// that are safe to repeat; see [`exec_command_once`].
+#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias, command = %command))]
+pub async fn exec_command(
+    host: &SshHost,
+    password: Option<&str>,
+    command: &str,
+    timeout: Option<Duration>,
+) -> anyhow::Result<CommandOutput> {
+    match try_exec_command(host, password, command, timeout).await {
+        Err(e) if is_connection_error(&e) => {
+            warn!(
+                "Connection to {} lost ({:#}), reconnecting once",
+                host.alias, e
+            );
+            invalidate_exec_session(host).await;
+            try_exec_command(host, password, command, timeout).await
+        }
+        result => result,
+    }
+}
+
+/// Like [`exec_command`], but never runs the command twice: it may have reached
+/// the server before the connection dropped, and repeating a `docker rm` would
+/// report a spurious "No such container". The dead session is still dropped so
+/// the next command reconnects.
+#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias, command = %command))]
+pub async fn exec_command_once(
+    host: &SshHost,
+    password: Option<&str>,
+    command: &str,
+    timeout: Option<Duration>,
+) -> anyhow::Result<CommandOutput> {
+    match try_exec_command(host, password, command, timeout).await {
+        Err(e) if is_connection_error(&e) => {
+            invalidate_exec_session(host).await;
+            Err(e.context(format!(
+                "Connection to {} lost; the action may or may not have been applied",
+                host.alias
+            )))
+        }
+        result => result,
+    }
+}
+
+async fn try_exec_command(
+    host: &SshHost,
+    password: Option<&str>,
+    command: &str,
+    timeout: Option<Duration>,
+) -> anyhow::Result<CommandOutput> {
+    let sess = establish_exec_session(host, password)
+        .await
+        .context("Failed to establish SSH session for remote command")?;
+    let in_use = use_exec_session(host);
+    let host = host.clone();
+    let command = command.to_string();
+
+    tokio::task::spawn_blocking(move || -> anyhow::Result<CommandOutput> {
+        let _in_use = in_use;
+        let limits = Limits {
+            cancel: CancelHandle::default(),
+            timeout,
+            started: Instant::now(),
+        };
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let exit_status = drive_command(
+            &sess,
+            &host,
+            &command,
+            &limits,
+            |source, data| match source {
+                Source::Stdout => stdout.extend_from_slice(data),
+                Source::Stderr => stderr.extend_from_slice(data),
+            },
+        )?;
+        let output = CommandOutput {
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+            exit_status,
+            duration: limits.started.elapsed(),
+        };
+        debug!(
+            "Command exited with {} after {:?} ({} bytes out, {} bytes err)",
+            output.exit_status,
+            output.duration,
+            stdout.len(),
+            stderr.len()
+        );
+        Ok(output)
+    })
+    .await?
+}
+
+/// Splits a byte stream into lines, keeping the unterminated tail for later.
+#[derive(Default)]
+struct LineSplitter {
+    partial: Vec<u8>,
+}
+
+impl LineSplitter {
+    fn push(&mut self, data: &[u8]) -> Vec<String> {
+        self.partial.extend_from_slice(data);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=pos).collect();
+            lines.push(Self::decode(&line[..pos]));
+        }
+        lines
+    }
+
+    fn finish(&mut self) -> Option<String> {
+        if self.partial.is_empty() {
+            return None;
+        }
+        let line = Self::decode(&self.partial);
+        self.partial.clear();
+        Some(line)
+    }
+
+    fn decode(line: &[u8]) -> String {
+        String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line)).into_owned()
+    }
+}
+
+/// Starts a command on the remote host and streams its output line by line.
+/// The command runs until it exits or [`CancelHandle::cancel`] is called.
+#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias, command = %command))]
+pub async fn stream_command(
+    host: &SshHost,
+    password: Option<&str>,
+    command: &str,
+) -> anyhow::Result<CommandStream> {
+    let sess = establish_exec_session(host, password)
+        .await
+        .context("Failed to establish SSH session for remote command")?;
+    let (line_tx, lines) = mpsc::unbounded_channel();
+    let (exit_tx, exit) = oneshot::channel();
+    let cancel = CancelHandle::default();
+
+    let limits = Limits {
+        cancel: cancel.clone(),
+        timeout: None,
+        started: Instant::now(),
+    };
+    let in_use = use_exec_session(host);
+    let host = host.clone();
+    let command = command.to_string();
+    std::thread::Builder::new()
+        .name(format!("ssh-exec-{}", host.alias))
+        .spawn(move || {
+            let _in_use = in_use;
+            let mut stdout = LineSplitter::default();
+            let mut stderr = LineSplitter::default();
+            let result = drive_command(&sess, &host, &command, &limits, |source, data| {
+                let (splitter, wrap): (_, fn(String) -> OutputLine) = match source {
+                    Source::Stdout => (&mut stdout, OutputLine::Stdout),
+                    Source::Stderr => (&mut stderr, OutputLine::Stderr),
+                };
+                for line in splitter.push(data) {
+                    let _ = line_tx.send(wrap(line));
+                }
+            });
+            if let Some(line) = stdout.finish() {
+                let _ = line_tx.send(OutputLine::Stdout(line));
+            }
+            if let Some(line) = stderr.finish() {
+                let _ = line_tx.send(OutputLine::Stderr(line));
+            }
+            drop(line_tx);
+            let _ = exit_tx.send(result.map(|exit_status| CommandExit {
+                exit_status,
+                duration: limits.started.elapsed(),
+            }));
+        })
+        .context("Failed to spawn command thread")?;
+
+    Ok(CommandStream {
+        lines,
+        cancel,
+        exit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits_lines_across_chunks() {
+        let mut splitter = LineSplitter::default();
+        assert!(splitter.push(b"hel").is_empty());
+        assert_eq!(splitter.push(b"lo\r\nwor"), vec!["hello"]);
+        assert_eq!(splitter.push(b"ld\n\n"), vec!["world", ""]);
+        assert_eq!(splitter.push(b"tail"), Vec::<String>::new());
+        assert_eq!(splitter.finish().as_deref(), Some("tail"));
+        assert_eq!(splitter.finish(), None);
+    }
+
+    #[test]
+    fn test_check_reports_stderr_of_failed_command() {
+        let output = CommandOutput {
+            stdout: String::new(),
+            stderr: "permission denied\n".to_string(),
+            exit_status: 1,
+            duration: Duration::ZERO,
+        };
+        let err = output.check().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Command exited with status 1: permission denied"
+        );
+    }
+
+    #[test]
+    fn test_setup_phase_is_bounded_and_shares_cancellation() {
+        let endless = Limits {
+            cancel: CancelHandle::default(),
+            timeout: None,
+            started: Instant::now(),
+        };
+        let setup = endless.setup_phase();
+        assert_eq!(setup.timeout, Some(SETUP_TIMEOUT));
+        endless.cancel.cancel();
+        assert!(setup.check().is_err());
+
+        let short = Limits {
+            timeout: Some(Duration::from_secs(5)),
+            ..Limits::setup()
+        };
+        assert!(short.setup_phase().timeout.unwrap() <= Duration::from_secs(5));
+    }
+}
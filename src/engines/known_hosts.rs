@@ -0,0 +1,271 @@
+use crate::config_observer::{SshHost, get_known_hosts_path};
+use anyhow::Context;
+use base64::Engine;
+use ssh2::{CheckResult, HashType, HostKeyType, KnownHostFileKind, KnownHostKeyFormat, Session};
+use std::io::Write;
+use std::sync::OnceLock;
+use tokio::sync::oneshot;
+use tracing::{info, instrument, warn};
+
+/// Identity of the key a server presented during the handshake.
+#[derive(Debug, Clone)]
+pub struct HostKeyInfo {
+    pub hostname: String,
+    pub port: u16,
+    /// OpenSSH key type name, e.g. `ssh-ed25519`.
+    pub key_type: &'static str,
+    /// OpenSSH-style `SHA256:<base64>` fingerprint.
+    pub fingerprint: String,
+}
+
+/// Question handed to the UI when a host key can't be trusted silently.
+#[derive(Debug, Clone)]
+pub enum HostKeyPrompt {
+    /// First connection to this host: the user decides whether to trust the key.
+    Unknown(HostKeyInfo),
+    /// The key differs from the one in `known_hosts`. The connection is always
+    /// refused; the prompt only exists so the user sees the warning.
+    Changed(HostKeyInfo),
+}
+
+type Prompter = Box<dyn Fn(HostKeyPrompt, oneshot::Sender<bool>) + Send + Sync>;
+
+static PROMPTER: OnceLock<Prompter> = OnceLock::new();
+
+/// Installs the callback used to ask the user about unknown or changed host keys.
+/// The callback must answer on the sender (`true` = trust); dropping it counts as a refusal.
+pub fn set_host_key_prompter(
+    prompter: impl Fn(HostKeyPrompt, oneshot::Sender<bool>) + Send + Sync + 'static,
+) {
+    let _ = PROMPTER.set(Box::new(prompter));
+}
+
+/// How a server's host key compares to `known_hosts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostKeyStatus {
+    Trusted,
+    Unknown,
+    Changed,
+}
+
+fn key_type_name(key_type: HostKeyType) -> &'static str {
+    match key_type {
+        HostKeyType::Rsa => "ssh-rsa",
+        HostKeyType::Dss => "ssh-dss",
+        HostKeyType::Ecdsa256 => "ecdsa-sha2-nistp256",
+        HostKeyType::Ecdsa384 => "ecdsa-sha2-nistp384",
+        HostKeyType::Ecdsa521 => "ecdsa-sha2-nistp521",
+        HostKeyType::Ed25519 => "ssh-ed25519",
+        HostKeyType::Unknown => "unknown",
+    }
+}
+
+/// Host pattern as written in `known_hosts`: bare for port 22, `[host]:port` otherwise.
+pub fn known_hosts_name(hostname: &str, port: u16) -> String {
+    if port == 22 {
+        hostname.to_string()
+    } else {
+        format!("[{}]:{}", hostname, port)
+    }
+}
+
+/// Formats the SHA-256 digest of a host key the way `ssh` prints it.
+fn format_fingerprint(hash: &[u8]) -> String {
+    format!(
+        "SHA256:{}",
+        base64::engine::general_purpose::STANDARD_NO_PAD.encode(hash)
+    )
+}
+
+/// Reads the session's host key and fingerprint. Blocking.
+fn host_key_info(sess: &Session, hostname: &str, port: u16) -> anyhow::Result<HostKeyInfo> {
+    let (_, key_type) = sess
+        .host_key()
+        .ok_or_else(|| anyhow::anyhow!("Server did not present a host key"))?;
+    let hash = sess
+        .host_key_hash(HashType::Sha256)
+        .ok_or_else(|| anyhow::anyhow!("Failed to hash the server host key"))?;
+    Ok(HostKeyInfo {
+        hostname: hostname.to_string(),
+        port,
+        key_type: key_type_name(key_type),
+        fingerprint: format_fingerprint(hash),
+    })
+}
+
+/// Looks the session's host key up in `known_hosts` (plain and hashed entries). Blocking.
+fn check_known_host(sess: &Session, hostname: &str, port: u16) -> anyhow::Result<HostKeyStatus> {
+    let (key, _) = sess
+        .host_key()
+        .ok_or_else(|| anyhow::anyhow!("Server did not present a host key"))?;
+    let mut known_hosts = sess
+        .known_hosts()
+        .context("Failed to initialize known_hosts")?;
+    if let Some(path) = get_known_hosts_path()
+        && path.exists()
+    {
+        known_hosts
+            .read_file(&path, KnownHostFileKind::OpenSSH)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+    }
+    match known_hosts.check_port(hostname, port, key) {
+        CheckResult::Match => Ok(HostKeyStatus::Trusted),
+        CheckResult::NotFound => Ok(HostKeyStatus::Unknown),
+        CheckResult::Mismatch => Ok(HostKeyStatus::Changed),
+        CheckResult::Failure => Err(anyhow::anyhow!(
+            "Failed to check the host key of {} against known_hosts",
+            hostname
+        )),
+    }
+}
+
+/// Appends the session's host key to `known_hosts`. Only the new line is written
+/// so existing comments, markers and hashed entries are left untouched. Blocking.
+fn append_known_host(sess: &Session, hostname: &str, port: u16) -> anyhow::Result<()> {
+    let path = get_known_hosts_path()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine known_hosts path"))?;
+    let (key, key_type) = sess
+        .host_key()
+        .ok_or_else(|| anyhow::anyhow!("Server did not present a host key"))?;
+
+    let mut known_hosts = sess
+        .known_hosts()
+        .context("Failed to initialize known_hosts")?;
+    known_hosts
+        .add(
+            &known_hosts_name(hostname, port),
+            key,
+            "",
+            KnownHostKeyFormat::from(key_type),
+        )
+        .context("Failed to add host key")?;
+    let entry = known_hosts
+        .hosts()?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Host key entry missing after insertion"))?;
+    let mut line = known_hosts
+        .write_string(&entry, KnownHostFileKind::OpenSSH)
+        .context("Failed to serialize host key entry")?;
+    if !line.ends_with('\n') {
+        line.push('\n');
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let needs_newline = std::fs::read(&path)
+        .map(|content| !content.is_empty() && !content.ends_with(b"\n"))
+        .unwrap_or(false);
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    if needs_newline {
+        file.write_all(b"\n")?;
+    }
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Asks the installed prompter and waits for the answer. No prompter means no trust.
+async fn ask_user(prompt: HostKeyPrompt) -> bool {
+    let Some(prompter) = PROMPTER.get() else {
+        warn!("No host key prompter installed, refusing");
+        return false;
+    };
+    let (tx, rx) = oneshot::channel();
+    prompter(prompt, tx);
+    rx.await.unwrap_or(false)
+}
+
+/// Reads the server's host key and compares it to `known_hosts`, without
+/// prompting or writing anything.
+pub async fn inspect_host_key(
+    sess: &Session,
+    host: &SshHost,
+) -> anyhow::Result<(HostKeyStatus, HostKeyInfo)> {
+    let sess = sess.clone();
+    let hostname = host.hostname.clone();
+    let port = host.port.unwrap_or(22);
+    tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+        let status = check_known_host(&sess, &hostname, port)?;
+        let info = host_key_info(&sess, &hostname, port)?;
+        Ok((status, info))
+    })
+    .await?
+}
+
+/// Verifies the host key of a freshly handshaken session against `known_hosts`.
+/// Unknown keys are trusted only after the user confirms the fingerprint (and are
+/// then recorded); changed keys are always refused.
+#[instrument(skip(sess), fields(host = %host.hostname, alias = %host.alias))]
+pub async fn verify_host_key(sess: &Session, host: &SshHost) -> anyhow::Result<()> {
+    let hostname = host.hostname.clone();
+    let port = host.port.unwrap_or(22);
+
+    let (status, info) = inspect_host_key(sess, host).await?;
+
+    match status {
+        HostKeyStatus::Trusted => {
+            tracing::trace!("Host key matches known_hosts");
+            Ok(())
+        }
+        HostKeyStatus::Unknown => {
+            info!(
+                "Unknown host key for {}: {} {}",
+                hostname, info.key_type, info.fingerprint
+            );
+            if !ask_user(HostKeyPrompt::Unknown(info.clone())).await {
+                anyhow::bail!(
+                    "Host key for {} ({} {}) was not accepted",
+                    hostname,
+                    info.key_type,
+                    info.fingerprint
+                );
+            }
+            let add_sess = sess.clone();
+            tokio::task::spawn_blocking(move || append_known_host(&add_sess, &hostname, port))
+                .await??;
+            info!("Host key for {} added to known_hosts", host.hostname);
+            Ok(())
+        }
+        HostKeyStatus::Changed => {
+            warn!(
+                "HOST KEY MISMATCH for {}: server presented {} {}",
+                hostname, info.key_type, info.fingerprint
+            );
+            let _ = ask_user(HostKeyPrompt::Changed(info.clone())).await;
+            Err(anyhow::anyhow!(
+                "WARNING: REMOTE HOST IDENTIFICATION HAS CHANGED for {}! \
+                 The server presented {} key {}, which does not match known_hosts. \
+                 Someone could be intercepting the connection. Refusing to connect.",
+                hostname,
+                info.key_type,
+                info.fingerprint
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_hosts_name_brackets_non_default_port() {
+        assert_eq!(known_hosts_name("example.com", 22), "example.com");
+        assert_eq!(known_hosts_name("example.com", 2222), "[example.com]:2222");
+    }
+
+    #[test]
+    fn test_format_fingerprint_matches_openssh_style() {
+        let fingerprint = format_fingerprint(&[0u8; 32]);
+        assert_eq!(
+            fingerprint,
+            "SHA256:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+        );
+    }
+}
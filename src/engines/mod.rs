@@ -1,4 +1,13 @@
+pub mod auth_prompt;
+pub mod diagnostics;
 pub mod docker;
+pub mod exec;
+pub mod known_hosts;
 pub mod monitor;
+pub mod proxy;
+pub mod relay;
 pub mod sftp;
+pub mod shell;
+pub mod socks;
 pub mod ssh;
+pub mod tunnel;
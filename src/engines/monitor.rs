@@ -1,7 +1,11 @@
 use crate::config_observer::SshHost;
-use crate::engines::ssh::run_remote_command;
+use crate::engines::exec::exec_command;
+use std::time::Duration;
 use tracing::{debug, instrument};
 
+/// Gives up on a metrics refresh when the host is this slow to answer.
+const METRICS_TIMEOUT: Duration = Duration::from_secs(15);
+
 /// Structure representing various system metrics retrieved from a host.
 #[derive(Debug, Default, Clone)]
 pub struct SystemMetrics {
@@ -43,7 +47,11 @@ pub async fn fetch_system_metrics(
                echo \"---DISK_ALL---\"; df -h / --output=pcent,used,size | awk 'NR==2 {print $1, $2, $3}'; \
                echo \"---CPU_P---\"; top -bn2 -d 0.2 | grep \"%Cpu\" | tail -1 | awk -F',' '{for(i=1;i<=NF;i++) if($i ~ /id/) print $i}' | awk '{print 100-$1}'";
 
-    let output = run_remote_command(host, password, cmd).await?;
+    // Some probes may be missing on the host; their sections just stay empty, so
+    // the exit status is not checked.
+    let output = exec_command(host, password, cmd, Some(METRICS_TIMEOUT))
+        .await?
+        .stdout;
     let mut metrics = SystemMetrics::default();
     let mut current_section = "";
 
@@ -0,0 +1,180 @@
+use crate::config_observer::{OutboundProxy, ProxyKind, SshHost};
+use crate::engines::socks;
+use anyhow::Context;
+use std::io::{BufRead, BufReader};
+use std::os::fd::OwnedFd;
+use std::os::unix::net::UnixStream;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+
+/// Upper bound on the headers of an HTTP CONNECT response.
+const MAX_HTTP_RESPONSE: usize = 8 * 1024;
+
+/// Expands the `ProxyCommand` tokens: `%h` host, `%p` port, `%r` user, `%n`
+/// alias as written and `%%` a literal percent sign.
+pub fn expand_proxy_command(command: &str, host: &SshHost) -> String {
+    let mut expanded = String::with_capacity(command.len());
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('h') => expanded.push_str(&host.hostname),
+            Some('p') => expanded.push_str(&host.port.unwrap_or(22).to_string()),
+            Some('r') => expanded.push_str(host.user.as_deref().unwrap_or("root")),
+            Some('n') => expanded.push_str(&host.alias),
+            Some('%') => expanded.push('%'),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+    expanded
+}
+
+/// Starts the host's `ProxyCommand` with one end of a socket pair as its stdin
+/// and stdout, and returns the other end as the SSH transport. The command exits
+/// on its own once the session closes the socket; its stderr goes to the log.
+pub fn spawn_proxy_command(host: &SshHost, command: &str) -> anyhow::Result<UnixStream> {
+    let expanded = expand_proxy_command(command, host);
+    info!("Starting ProxyCommand for {}: {}", host.alias, expanded);
+    let (local, remote) = UnixStream::pair().context("Failed to create socket pair")?;
+    let stdin = OwnedFd::from(remote.try_clone()?);
+    let stdout = OwnedFd::from(remote);
+
+    let mut child = Command::new("/bin/sh")
+        .arg("-c")
+        .arg(format!("exec {}", expanded))
+        .stdin(Stdio::from(stdin))
+        .stdout(Stdio::from(stdout))
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start ProxyCommand: {}", expanded))?;
+
+    let stderr = child.stderr.take();
+    let alias = host.alias.clone();
+    std::thread::Builder::new()
+        .name(format!("proxy-command-{}", alias))
+        .spawn(move || {
+            if let Some(stderr) = stderr {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    warn!("ProxyCommand ({}): {}", alias, line);
+                }
+            }
+            match child.wait() {
+                Ok(status) => debug!("ProxyCommand for {} exited: {}", alias, status),
+                Err(e) => warn!("Failed to wait for ProxyCommand of {}: {}", alias, e),
+            }
+        })
+        .context("Failed to spawn ProxyCommand thread")?;
+
+    Ok(local)
+}
+
+/// Connects to `host:port` through the app-wide outbound proxy.
+pub async fn connect_via_proxy(
+    proxy: &OutboundProxy,
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> anyhow::Result<TcpStream> {
+    let proxy_addr = format!("{}:{}", proxy.host, proxy.port);
+    debug!(
+        "Connecting to {}:{} through proxy {}",
+        host, port, proxy_addr
+    );
+    let connect = async {
+        let mut stream = TcpStream::connect(&proxy_addr)
+            .await
+            .with_context(|| format!("Failed to connect to proxy {}", proxy_addr))?;
+        match proxy.kind {
+            ProxyKind::Socks5 => socks::connect_request(&mut stream, host, port).await?,
+            ProxyKind::HttpConnect => http_connect(&mut stream, host, port).await?,
+            ProxyKind::None => anyhow::bail!("No outbound proxy configured"),
+        }
+        Ok(stream)
+    };
+    tokio::time::timeout(timeout, connect)
+        .await
+        .map_err(|_| anyhow::anyhow!("Proxy {} did not answer in time", proxy_addr))?
+}
+
+/// Sends an HTTP `CONNECT` request and checks the proxy answered with 2xx.
+/// Reads the response one byte at a time so nothing past the headers (the SSH
+/// banner) is consumed.
+async fn http_connect(stream: &mut TcpStream, host: &str, port: u16) -> anyhow::Result<()> {
+    let authority = if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    stream
+        .write_all(
+            format!(
+                "CONNECT {0} HTTP/1.1\r\nHost: {0}\r\nProxy-Connection: Keep-Alive\r\n\r\n",
+                authority
+            )
+            .as_bytes(),
+        )
+        .await?;
+
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_HTTP_RESPONSE {
+            anyhow::bail!("HTTP proxy response is too long");
+        }
+        let byte = stream
+            .read_u8()
+            .await
+            .context("HTTP proxy closed the connection")?;
+        response.push(byte);
+    }
+    check_http_connect_response(&String::from_utf8_lossy(&response))
+}
+
+fn check_http_connect_response(response: &str) -> anyhow::Result<()> {
+    let status_line = response.lines().next().unwrap_or_default();
+    let mut parts = status_line.splitn(3, ' ');
+    let version = parts.next().unwrap_or_default();
+    let code = parts.next().and_then(|code| code.parse::<u16>().ok());
+    match code {
+        Some(200..=299) if version.starts_with("HTTP/") => Ok(()),
+        _ => anyhow::bail!("HTTP proxy refused CONNECT: {}", status_line.trim()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_command_tokens_are_expanded() {
+        let host = SshHost {
+            alias: "prod".to_string(),
+            hostname: "10.0.0.5".to_string(),
+            user: Some("deploy".to_string()),
+            port: Some(2222),
+            ..Default::default()
+        };
+        assert_eq!(
+            expand_proxy_command("ssh -W %h:%p %r@gw # %n 100%% %x", &host),
+            "ssh -W 10.0.0.5:2222 deploy@gw # prod 100% %x"
+        );
+    }
+
+    #[test]
+    fn test_http_connect_accepts_only_success() {
+        assert!(check_http_connect_response("HTTP/1.1 200 Connection established\r\n\r\n").is_ok());
+        assert!(
+            check_http_connect_response("HTTP/1.0 407 Proxy Authentication Required\r\n").is_err()
+        );
+        assert!(check_http_connect_response("SSH-2.0-OpenSSH_9.6\r\n").is_err());
+    }
+}
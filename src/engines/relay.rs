@@ -0,0 +1,187 @@
+use anyhow::Context;
+use ssh2::{Channel, Session};
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+use tracing::debug;
+
+pub const BUF_SIZE: usize = 32 * 1024;
+/// Longest pause between polls while every relay is idle.
+const MAX_IDLE_SLEEP: Duration = Duration::from_millis(10);
+
+/// `LIBSSH2_ERROR_EAGAIN`, returned by non-blocking libssh2 calls that would block.
+const LIBSSH2_ERROR_EAGAIN: i32 = -37;
+
+/// Returns `true` for the "try again" error a non-blocking libssh2 call yields.
+pub fn would_block(e: &ssh2::Error) -> bool {
+    e.code() == ssh2::ErrorCode::Session(LIBSSH2_ERROR_EAGAIN)
+}
+
+/// Exponential back-off used by relay loops while nothing moves, so an idle
+/// tunnel doesn't spin a core but an active one stays responsive.
+pub struct IdleBackoff(Duration);
+
+impl IdleBackoff {
+    pub fn new() -> Self {
+        Self(Duration::from_millis(1))
+    }
+
+    pub fn reset(&mut self) {
+        self.0 = Duration::from_millis(1);
+    }
+
+    pub fn sleep(&mut self) {
+        std::thread::sleep(self.0);
+        self.0 = (self.0 * 2).min(MAX_IDLE_SLEEP);
+    }
+}
+
+/// Bytes moved by a single [`Relay::pump`] call.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PumpProgress {
+    /// Bytes written to the SSH channel.
+    pub sent: usize,
+    /// Bytes written to the local socket.
+    pub received: usize,
+    pub progressed: bool,
+    pub done: bool,
+}
+
+/// Shuttles bytes between one SSH channel and a local socket. Both must be in
+/// non-blocking mode; the owner calls [`Relay::pump`] in a loop.
+pub struct Relay<L> {
+    channel: Channel,
+    local: L,
+    to_local: Vec<u8>,
+    to_remote: Vec<u8>,
+    local_eof: bool,
+    eof_sent: bool,
+}
+
+impl<L: Read + Write> Relay<L> {
+    pub fn new(channel: Channel, local: L) -> Self {
+        Self {
+            channel,
+            local,
+            to_local: Vec::new(),
+            to_remote: Vec::new(),
+            local_eof: false,
+            eof_sent: false,
+        }
+    }
+
+    /// Moves whatever is ready in both directions without blocking.
+    pub fn pump(&mut self, buf: &mut [u8]) -> std::io::Result<PumpProgress> {
+        let mut progress = PumpProgress::default();
+
+        if self.to_local.is_empty() {
+            match self.channel.read(buf) {
+                Ok(0) if self.channel.eof() => {
+                    progress.done = true;
+                    return Ok(progress);
+                }
+                Ok(0) => {}
+                Ok(n) => {
+                    self.to_local.extend_from_slice(&buf[..n]);
+                    progress.progressed = true;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if !self.to_local.is_empty() {
+            match self.local.write(&self.to_local) {
+                Ok(n) => {
+                    self.to_local.drain(..n);
+                    progress.received += n;
+                    progress.progressed = true;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        if self.to_remote.is_empty() && !self.local_eof {
+            match self.local.read(buf) {
+                Ok(0) => {
+                    self.local_eof = true;
+                    progress.progressed = true;
+                }
+                Ok(n) => {
+                    self.to_remote.extend_from_slice(&buf[..n]);
+                    progress.progressed = true;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if !self.to_remote.is_empty() {
+            match self.channel.write(&self.to_remote) {
+                Ok(n) => {
+                    self.to_remote.drain(..n);
+                    progress.sent += n;
+                    progress.progressed = true;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Half-close: forward the local EOF and keep draining the remote side.
+        if self.local_eof && self.to_remote.is_empty() && !self.eof_sent {
+            match self.channel.send_eof() {
+                Ok(()) => self.eof_sent = true,
+                Err(e) if would_block(&e) => {}
+                Err(_) => progress.done = true,
+            }
+        }
+        Ok(progress)
+    }
+
+    pub fn channel_mut(&mut self) -> &mut Channel {
+        &mut self.channel
+    }
+
+    /// Closes the channel. Best effort: the peer may already be gone.
+    pub fn close(mut self) {
+        let _ = self.channel.close();
+    }
+}
+
+/// Bridges an SSH channel to one end of a local socket pair and returns the other
+/// end, so the channel can be used wherever a file descriptor is required (e.g. as
+/// the transport of another `Session` when hopping through a bastion).
+///
+/// The session is switched to non-blocking mode and driven by a dedicated thread,
+/// so it must not be shared with other users. The bridge stops, closing the
+/// channel, when either side hangs up.
+pub fn spawn_channel_bridge(sess: Session, channel: Channel) -> anyhow::Result<UnixStream> {
+    let (local, remote) = UnixStream::pair().context("Failed to create socket pair")?;
+    local.set_nonblocking(true)?;
+    sess.set_blocking(false);
+
+    std::thread::Builder::new()
+        .name("ssh-channel-bridge".to_string())
+        .spawn(move || {
+            // Keep the session alive for as long as the channel is in use.
+            let _sess = sess;
+            let mut relay = Relay::new(channel, local);
+            let mut buf = vec![0u8; BUF_SIZE];
+            let mut backoff = IdleBackoff::new();
+            loop {
+                match relay.pump(&mut buf) {
+                    Ok(progress) if progress.done => break,
+                    Ok(progress) if progress.progressed => backoff.reset(),
+                    Ok(_) => backoff.sleep(),
+                    Err(e) => {
+                        debug!("Channel bridge closed: {}", e);
+                        break;
+                    }
+                }
+            }
+            relay.close();
+        })
+        .context("Failed to spawn channel bridge thread")?;
+
+    Ok(remote)
+}
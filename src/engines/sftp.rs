@@ -1,4 +1,8 @@
 use crate::config_observer::SshHost;
+use crate::engines::ssh::{
+    invalidate_pooled_session, is_connection_error, pool_key, touch_pooled_session,
+    use_pooled_session,
+};
 use anyhow::Context;
 use ssh2::Session;
 use std::collections::HashMap;
@@ -32,11 +36,7 @@ async fn get_or_connect_sftp(
     host: &SshHost,
     password: Option<&str>,
 ) -> anyhow::Result<Arc<ActiveSession>> {
-    let host_key = format!(
-        "{}@{}",
-        host.user.as_deref().unwrap_or("root"),
-        host.hostname
-    );
+    let host_key = pool_key(host);
 
     let host_lock = {
         let mut pool = get_session_pool()
@@ -53,6 +53,7 @@ async fn get_or_connect_sftp(
         debug!("Checking existing SFTP session");
         if active.sftp.stat(Path::new(".")).is_ok() {
             debug!("Using existing SFTP session");
+            touch_pooled_session(host);
             return Ok(active.clone());
         }
         debug!("Existing SFTP session stale, reconnecting");
@@ -77,6 +78,76 @@ async fn get_or_connect_sftp(
     Ok(active)
 }
 
+/// Forgets the pooled SFTP session of a host. Used when the underlying SSH session
+/// is evicted or found dead; operations in flight keep their own handle.
+pub(crate) fn forget_sftp_session(host_key: &str) {
+    if let Ok(mut pool) = get_session_pool().lock() {
+        pool.remove(host_key);
+    }
+}
+
+/// Runs a blocking SFTP operation on the pooled session. If it fails because the
+/// connection dropped (e.g. after a suspend), reconnects and retries it once.
+/// Only for operations that are safe to repeat; see [`with_sftp_once`].
+async fn with_sftp<T, F>(host: &SshHost, password: Option<&str>, op: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: Fn(&ActiveSession) -> anyhow::Result<T> + Send + Sync + 'static,
+{
+    let op = Arc::new(op);
+    match run_sftp(host, password, op.clone()).await {
+        Err(e) if is_connection_error(&e) => {
+            warn!(
+                "SFTP connection to {} lost ({:#}), reconnecting once",
+                host.alias, e
+            );
+            drop_sftp_connection(host).await;
+            run_sftp(host, password, op).await
+        }
+        result => result,
+    }
+}
+
+/// Like [`with_sftp`], but never replays the operation: a rename, delete or
+/// create may have reached the server before the connection dropped, and
+/// repeating it would report a spurious "no such file" or "already exists".
+/// The dead session is still dropped so the next operation reconnects.
+async fn with_sftp_once<T, F>(host: &SshHost, password: Option<&str>, op: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: Fn(&ActiveSession) -> anyhow::Result<T> + Send + Sync + 'static,
+{
+    match run_sftp(host, password, Arc::new(op)).await {
+        Err(e) if is_connection_error(&e) => {
+            drop_sftp_connection(host).await;
+            Err(e.context(format!(
+                "Connection to {} dropped; the change may or may not have been applied",
+                host.alias
+            )))
+        }
+        result => result,
+    }
+}
+
+async fn run_sftp<T, F>(host: &SshHost, password: Option<&str>, op: Arc<F>) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: Fn(&ActiveSession) -> anyhow::Result<T> + Send + Sync + 'static,
+{
+    let active = get_or_connect_sftp(host, password).await?;
+    let in_use = use_pooled_session(host);
+    tokio::task::spawn_blocking(move || {
+        let _in_use = in_use;
+        op(&active)
+    })
+    .await?
+}
+
+async fn drop_sftp_connection(host: &SshHost) {
+    forget_sftp_session(&pool_key(host));
+    invalidate_pooled_session(host).await;
+}
+
 /// Lists files in the specified remote directory.
 #[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias, path = %path))]
 pub async fn list_files(
@@ -85,34 +156,37 @@ pub async fn list_files(
     path: &str,
 ) -> anyhow::Result<Vec<RemoteFile>> {
     debug!("Listing files in {}", path);
-    let active = get_or_connect_sftp(host, password).await?;
     let path_owned = path.to_string();
-    tokio::task::spawn_blocking(move || -> anyhow::Result<Vec<RemoteFile>> {
-        let dir = active
-            .sftp
-            .readdir(Path::new(&path_owned))
-            .with_context(|| format!("Failed to read directory: {}", path_owned))?;
-        let mut files = Vec::new();
-        for (path, stat) in dir {
-            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                files.push(RemoteFile {
-                    name: name.to_string(),
-                    is_dir: stat.is_dir(),
-                    size: stat.size.unwrap_or(0),
-                });
-            }
-        }
-        files.sort_by(|a, b| {
-            if a.is_dir != b.is_dir {
-                b.is_dir.cmp(&a.is_dir)
-            } else {
-                a.name.to_lowercase().cmp(&b.name.to_lowercase())
+    with_sftp(
+        host,
+        password,
+        move |active| -> anyhow::Result<Vec<RemoteFile>> {
+            let dir = active
+                .sftp
+                .readdir(Path::new(&path_owned))
+                .with_context(|| format!("Failed to read directory: {}", path_owned))?;
+            let mut files = Vec::new();
+            for (path, stat) in dir {
+                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                    files.push(RemoteFile {
+                        name: name.to_string(),
+                        is_dir: stat.is_dir(),
+                        size: stat.size.unwrap_or(0),
+                    });
+                }
             }
-        });
+            files.sort_by(|a, b| {
+                if a.is_dir != b.is_dir {
+                    b.is_dir.cmp(&a.is_dir)
+                } else {
+                    a.name.to_lowercase().cmp(&b.name.to_lowercase())
+                }
+            });
 
-        Ok(files)
-    })
-    .await?
+            Ok(files)
+        },
+    )
+    .await
 }
 
 /// Deletes a file or directory on the remote host.
@@ -124,9 +198,8 @@ pub async fn delete_file(
     is_dir: bool,
 ) -> anyhow::Result<()> {
     info!("Deleting {} (dir: {})", path, is_dir);
-    let active = get_or_connect_sftp(host, password).await?;
     let path_owned = path.to_string();
-    tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+    with_sftp_once(host, password, move |active| -> anyhow::Result<()> {
         let p = Path::new(&path_owned);
         if is_dir {
             active
@@ -141,39 +214,37 @@ pub async fn delete_file(
         }
         Ok(())
     })
-    .await?
+    .await
 }
 
 /// Creates a new directory on the remote host.
 #[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias, path = %path))]
 pub async fn create_dir(host: &SshHost, password: Option<&str>, path: &str) -> anyhow::Result<()> {
     info!("Creating directory {}", path);
-    let active = get_or_connect_sftp(host, password).await?;
     let path_owned = path.to_string();
-    tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+    with_sftp_once(host, password, move |active| -> anyhow::Result<()> {
         active
             .sftp
             .mkdir(Path::new(&path_owned), 0o755)
             .with_context(|| format!("Failed to create directory: {}", path_owned))?;
         Ok(())
     })
-    .await?
+    .await
 }
 
 /// Creates an empty file on the remote host.
 #[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias, path = %path))]
 pub async fn create_file(host: &SshHost, password: Option<&str>, path: &str) -> anyhow::Result<()> {
     info!("Creating file {}", path);
-    let active = get_or_connect_sftp(host, password).await?;
     let path_owned = path.to_string();
-    tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+    with_sftp_once(host, password, move |active| -> anyhow::Result<()> {
         active
             .sftp
             .create(Path::new(&path_owned))
             .with_context(|| format!("Failed to create file: {}", path_owned))?;
         Ok(())
     })
-    .await?
+    .await
 }
 
 /// Renames or moves a file or directory on the remote host.
@@ -185,17 +256,16 @@ pub async fn rename_file(
     new_path: &str,
 ) -> anyhow::Result<()> {
     info!("Renaming {} to {}", old_path, new_path);
-    let active = get_or_connect_sftp(host, password).await?;
     let old_owned = old_path.to_string();
     let new_owned = new_path.to_string();
-    tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+    with_sftp_once(host, password, move |active| -> anyhow::Result<()> {
         active
             .sftp
             .rename(Path::new(&old_owned), Path::new(&new_owned), None)
             .with_context(|| format!("Failed to rename {} to {}", old_owned, new_owned))?;
         Ok(())
     })
-    .await?
+    .await
 }
 
 /// Uploads a local file to the remote host.
@@ -207,10 +277,9 @@ pub async fn upload_file(
     remote_path: &str,
 ) -> anyhow::Result<()> {
     info!("Uploading {} to {}", local_path, remote_path);
-    let active = get_or_connect_sftp(host, password).await?;
     let local_owned = local_path.to_string();
     let remote_owned = remote_path.to_string();
-    tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+    with_sftp(host, password, move |active| -> anyhow::Result<()> {
         let mut local_file = std::fs::File::open(&local_owned)
             .with_context(|| format!("Failed to open local file for upload: {}", local_owned))?;
         let mut remote_file = active
@@ -221,7 +290,7 @@ pub async fn upload_file(
             .context("Failed to copy data during upload")?;
         Ok(())
     })
-    .await?
+    .await
 }
 
 /// Downloads a remote file to the local filesystem.
@@ -233,10 +302,9 @@ pub async fn download_file(
     local_path: &str,
 ) -> anyhow::Result<()> {
     info!("Downloading {} to {}", remote_path, local_path);
-    let active = get_or_connect_sftp(host, password).await?;
     let remote_owned = remote_path.to_string();
     let local_owned = local_path.to_string();
-    tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+    with_sftp(host, password, move |active| -> anyhow::Result<()> {
         let mut remote_file = active
             .sftp
             .open(Path::new(&remote_owned))
@@ -247,7 +315,7 @@ pub async fn download_file(
             .context("Failed to copy data during download")?;
         Ok(())
     })
-    .await?
+    .await
 }
 
 /// Synchronously downloads a remote file using a provided Tokio runtime handle.
@@ -260,17 +328,11 @@ pub fn download_file_sync(
     local_path: String,
 ) -> anyhow::Result<()> {
     info!("Sync downloading {} to {}", remote_path, local_path);
-    let active = rt
-        .block_on(get_or_connect_sftp(&host, password.as_deref()))
-        .context("Failed to connect for sync download")?;
-
-    let mut remote_file = active
-        .sftp
-        .open(Path::new(&remote_path))
-        .with_context(|| format!("Failed to open remote file: {}", remote_path))?;
-    let mut local_file = std::fs::File::create(&local_path)
-        .with_context(|| format!("Failed to create local file: {}", local_path))?;
-    std::io::copy(&mut remote_file, &mut local_file)
-        .context("Failed to copy data during sync download")?;
-    Ok(())
+    rt.block_on(download_file(
+        &host,
+        password.as_deref(),
+        &remote_path,
+        &local_path,
+    ))
+    .context("Sync download failed")
 }
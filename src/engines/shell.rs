@@ -0,0 +1,189 @@
+use crate::config_observer::SshHost;
+use crate::engines::exec::{Limits, open_channel, retry};
+use crate::engines::relay::{BUF_SIZE, IdleBackoff, Relay, would_block};
+use crate::engines::ssh::{
+    ExecSession, establish_exec_session, touch_exec_session, use_exec_session,
+};
+use anyhow::Context;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use tokio::sync::oneshot;
+use tracing::{debug, info, instrument, warn};
+
+/// Terminal type announced to the server; matches what VTE emulates.
+const TERM: &str = "xterm-256color";
+/// Size used until the terminal widget reports its own.
+const DEFAULT_SIZE: (u32, u32) = (80, 24);
+
+/// Opens a local pseudo-terminal pair. The master goes to the terminal widget;
+/// the slave is handed to [`run_shell`], which relays it to the remote pty.
+pub fn open_local_pty() -> anyhow::Result<(OwnedFd, File)> {
+    let (mut master, mut slave) = (-1, -1);
+    // SAFETY: both out-pointers are valid; name, termios and winsize may be null.
+    let ret = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error()).context("Failed to open a pseudo-terminal");
+    }
+    // SAFETY: openpty succeeded, so both descriptors are open and owned by us.
+    let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+
+    // The remote pty does the line editing and echo; locally, bytes pass through
+    // untouched and reads must not block the relay thread.
+    // SAFETY: `slave` is a valid terminal descriptor for the duration of these calls.
+    unsafe {
+        let mut termios = std::mem::zeroed::<libc::termios>();
+        if libc::tcgetattr(slave.as_raw_fd(), &mut termios) != 0 {
+            return Err(std::io::Error::last_os_error()).context("Failed to read pty attributes");
+        }
+        libc::cfmakeraw(&mut termios);
+        if libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &termios) != 0 {
+            return Err(std::io::Error::last_os_error()).context("Failed to set pty raw mode");
+        }
+        let flags = libc::fcntl(slave.as_raw_fd(), libc::F_GETFL);
+        if flags < 0 || libc::fcntl(slave.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK) < 0
+        {
+            return Err(std::io::Error::last_os_error()).context("Failed to make pty non-blocking");
+        }
+    }
+    Ok((master, File::from(slave)))
+}
+
+/// Columns and rows the terminal widget set on the pty, if any.
+fn window_size(pty: &File) -> Option<(u32, u32)> {
+    // SAFETY: TIOCGWINSZ only writes a `winsize` into the provided struct.
+    let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+    let ret = unsafe { libc::ioctl(pty.as_raw_fd(), libc::TIOCGWINSZ, &mut size) };
+    (ret == 0 && size.ws_col > 0 && size.ws_row > 0)
+        .then_some((u32::from(size.ws_col), u32::from(size.ws_row)))
+}
+
+/// The slave end of the local pty. Once the widget closes the master, reads return
+/// EOF or `EIO` depending on the kernel; both are reported as `BrokenPipe`.
+struct PtySlave(File);
+
+impl PtySlave {
+    fn hangup(result: std::io::Result<usize>) -> std::io::Result<usize> {
+        match result {
+            Ok(0) => Err(ErrorKind::BrokenPipe.into()),
+            Err(e) if e.raw_os_error() == Some(libc::EIO) => Err(ErrorKind::BrokenPipe.into()),
+            result => result,
+        }
+    }
+}
+
+impl Read for PtySlave {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Self::hangup(self.0.read(buf))
+    }
+}
+
+impl Write for PtySlave {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Self::hangup(self.0.write(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Starts an interactive shell on the host and relays it to the local pty slave
+/// until either side hangs up. Window size changes made by the terminal widget
+/// are forwarded to the remote pty. Returns the shell's exit status, or `None`
+/// when the terminal was closed first.
+#[instrument(skip(password, pty), fields(host = %host.hostname, alias = %host.alias))]
+pub async fn run_shell(
+    host: &SshHost,
+    password: Option<&str>,
+    pty: File,
+) -> anyhow::Result<Option<i32>> {
+    let sess = establish_exec_session(host, password)
+        .await
+        .context("Failed to establish SSH session for the terminal")?;
+    let size_probe = pty.try_clone().context("Failed to duplicate pty")?;
+    let (tx, rx) = oneshot::channel();
+    let in_use = use_exec_session(host);
+    let host = host.clone();
+
+    std::thread::Builder::new()
+        .name(format!("ssh-shell-{}", host.alias))
+        .spawn(move || {
+            let _in_use = in_use;
+            let _ = tx.send(relay_shell(&sess, &host, pty, &size_probe));
+        })
+        .context("Failed to spawn shell thread")?;
+
+    rx.await
+        .map_err(|_| anyhow::anyhow!("Shell thread stopped unexpectedly"))?
+}
+
+fn relay_shell(
+    sess: &ExecSession,
+    host: &SshHost,
+    pty: File,
+    size_probe: &File,
+) -> anyhow::Result<Option<i32>> {
+    let limits = Limits::setup();
+    let mut channel = open_channel(sess, &limits)?;
+    let mut size = window_size(size_probe).unwrap_or(DEFAULT_SIZE);
+    retry(&limits, || {
+        channel.request_pty(TERM, None, Some((size.0, size.1, 0, 0)))
+    })
+    .context("Failed to request a remote pty")?;
+    retry(&limits, || channel.shell()).context("Failed to start remote shell")?;
+    info!("Shell started on {}", host.alias);
+
+    let mut relay = Relay::new(channel, PtySlave(pty));
+    let mut buf = vec![0u8; BUF_SIZE];
+    let mut backoff = IdleBackoff::new();
+    loop {
+        match relay.pump(&mut buf) {
+            Ok(progress) if progress.done => break,
+            Ok(progress) if progress.progressed => {
+                touch_exec_session(host);
+                backoff.reset();
+            }
+            Ok(_) => backoff.sleep(),
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => {
+                debug!("Terminal closed, hanging up shell on {}", host.alias);
+                relay.close();
+                return Ok(None);
+            }
+            Err(e) => {
+                relay.close();
+                return Err(e).context("Terminal connection lost");
+            }
+        }
+
+        if let Some(new_size) = window_size(size_probe)
+            && new_size != size
+        {
+            match relay
+                .channel_mut()
+                .request_pty_size(new_size.0, new_size.1, None, None)
+            {
+                Ok(()) => size = new_size,
+                Err(e) if would_block(&e) => {}
+                Err(e) => {
+                    warn!("Failed to resize remote pty: {}", e);
+                    size = new_size;
+                }
+            }
+        }
+    }
+
+    let limits = Limits::setup();
+    let channel = relay.channel_mut();
+    retry(&limits, || channel.close())?;
+    retry(&limits, || channel.wait_close())?;
+    Ok(Some(channel.exit_status()?))
+}
@@ -0,0 +1,217 @@
+use anyhow::Context;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const SOCKS_VERSION: u8 = 0x05;
+const AUTH_NONE: u8 = 0x00;
+const AUTH_NO_ACCEPTABLE: u8 = 0xFF;
+const CMD_CONNECT: u8 = 0x01;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+pub const REPLY_SUCCEEDED: u8 = 0x00;
+pub const REPLY_GENERAL_FAILURE: u8 = 0x01;
+pub const REPLY_CONNECTION_REFUSED: u8 = 0x05;
+const REPLY_COMMAND_NOT_SUPPORTED: u8 = 0x07;
+const REPLY_ADDRESS_NOT_SUPPORTED: u8 = 0x08;
+
+/// Builds a SOCKS5 reply. The bound address is always reported as `0.0.0.0:0`
+/// since the real one lives on the server side of the SSH channel.
+pub fn reply(code: u8) -> [u8; 10] {
+    [SOCKS_VERSION, code, 0x00, ATYP_IPV4, 0, 0, 0, 0, 0, 0]
+}
+
+/// Runs the server side of a SOCKS5 handshake (RFC 1928) up to the request:
+/// only "no authentication" and the CONNECT command are supported. Returns the
+/// requested target; the caller sends the final reply once it knows whether the
+/// target is reachable.
+pub async fn read_connect_request<S>(stream: &mut S) -> anyhow::Result<(String, u16)>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut header = [0u8; 2];
+    stream
+        .read_exact(&mut header)
+        .await
+        .context("Failed to read SOCKS greeting")?;
+    if header[0] != SOCKS_VERSION {
+        anyhow::bail!("Unsupported SOCKS version {}", header[0]);
+    }
+    let mut methods = vec![0u8; header[1] as usize];
+    stream.read_exact(&mut methods).await?;
+    if !methods.contains(&AUTH_NONE) {
+        stream
+            .write_all(&[SOCKS_VERSION, AUTH_NO_ACCEPTABLE])
+            .await?;
+        anyhow::bail!("SOCKS client requires authentication");
+    }
+    stream.write_all(&[SOCKS_VERSION, AUTH_NONE]).await?;
+
+    let mut request = [0u8; 4];
+    stream
+        .read_exact(&mut request)
+        .await
+        .context("Failed to read SOCKS request")?;
+    if request[1] != CMD_CONNECT {
+        stream
+            .write_all(&reply(REPLY_COMMAND_NOT_SUPPORTED))
+            .await?;
+        anyhow::bail!("Unsupported SOCKS command {}", request[1]);
+    }
+
+    let host = match request[3] {
+        ATYP_IPV4 => {
+            let mut addr = [0u8; 4];
+            stream.read_exact(&mut addr).await?;
+            Ipv4Addr::from(addr).to_string()
+        }
+        ATYP_IPV6 => {
+            let mut addr = [0u8; 16];
+            stream.read_exact(&mut addr).await?;
+            Ipv6Addr::from(addr).to_string()
+        }
+        ATYP_DOMAIN => {
+            let len = stream.read_u8().await? as usize;
+            let mut name = vec![0u8; len];
+            stream.read_exact(&mut name).await?;
+            String::from_utf8(name).context("SOCKS domain name is not valid UTF-8")?
+        }
+        other => {
+            stream
+                .write_all(&reply(REPLY_ADDRESS_NOT_SUPPORTED))
+                .await?;
+            anyhow::bail!("Unsupported SOCKS address type {}", other);
+        }
+    };
+    let port = stream.read_u16().await?;
+    Ok((host, port))
+}
+
+/// Runs the client side of a SOCKS5 handshake asking the proxy to CONNECT to
+/// `host:port`. Host names are sent as-is so the proxy resolves them.
+pub async fn connect_request<S>(stream: &mut S, host: &str, port: u16) -> anyhow::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream.write_all(&[SOCKS_VERSION, 1, AUTH_NONE]).await?;
+    let mut method = [0u8; 2];
+    stream
+        .read_exact(&mut method)
+        .await
+        .context("SOCKS proxy closed the connection")?;
+    if method != [SOCKS_VERSION, AUTH_NONE] {
+        anyhow::bail!("SOCKS proxy requires an unsupported authentication method");
+    }
+
+    let mut request = vec![SOCKS_VERSION, CMD_CONNECT, 0x00];
+    match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => {
+            request.push(ATYP_IPV4);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(std::net::IpAddr::V6(ip)) => {
+            request.push(ATYP_IPV6);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            let len = u8::try_from(host.len()).context("Host name too long for SOCKS")?;
+            request.push(ATYP_DOMAIN);
+            request.push(len);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut header = [0u8; 4];
+    stream
+        .read_exact(&mut header)
+        .await
+        .context("Failed to read SOCKS reply")?;
+    if header[1] != REPLY_SUCCEEDED {
+        anyhow::bail!(
+            "SOCKS proxy could not connect to {}:{} (reply {})",
+            host,
+            port,
+            header[1]
+        );
+    }
+    let bound_len = match header[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => stream.read_u8().await? as usize,
+        other => anyhow::bail!("Unsupported SOCKS address type {}", other),
+    };
+    let mut bound = vec![0u8; bound_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_connect_request_domain() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let mut request = vec![0x05, 0x01, 0x00, 0x05, 0x01, 0x00, 0x03, 11];
+        request.extend_from_slice(b"example.com");
+        request.extend_from_slice(&443u16.to_be_bytes());
+        client.write_all(&request).await.unwrap();
+
+        let target = read_connect_request(&mut server).await.unwrap();
+        assert_eq!(target, ("example.com".to_string(), 443));
+
+        let mut method = [0u8; 2];
+        client.read_exact(&mut method).await.unwrap();
+        assert_eq!(method, [0x05, 0x00]);
+    }
+
+    #[tokio::test]
+    async fn test_read_connect_request_ipv6_and_rejects_bind() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let mut request = vec![0x05, 0x01, 0x00, 0x05, 0x01, 0x00, 0x04];
+        request.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        request.extend_from_slice(&8080u16.to_be_bytes());
+        client.write_all(&request).await.unwrap();
+        let target = read_connect_request(&mut server).await.unwrap();
+        assert_eq!(target, ("::1".to_string(), 8080));
+
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client
+            .write_all(&[
+                0x05, 0x01, 0x00, 0x05, 0x02, 0x00, 0x01, 127, 0, 0, 1, 0, 80,
+            ])
+            .await
+            .unwrap();
+        assert!(read_connect_request(&mut server).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_request_sends_domain_and_reads_reply() {
+        let (mut client, mut proxy) = tokio::io::duplex(128);
+        let proxy_side = tokio::spawn(async move {
+            let target = read_connect_request(&mut proxy).await.unwrap();
+            proxy.write_all(&reply(REPLY_SUCCEEDED)).await.unwrap();
+            target
+        });
+        connect_request(&mut client, "git.example.com", 22)
+            .await
+            .unwrap();
+        assert_eq!(
+            proxy_side.await.unwrap(),
+            ("git.example.com".to_string(), 22)
+        );
+
+        let (mut client, mut proxy) = tokio::io::duplex(128);
+        tokio::spawn(async move {
+            read_connect_request(&mut proxy).await.unwrap();
+            proxy
+                .write_all(&reply(REPLY_CONNECTION_REFUSED))
+                .await
+                .unwrap();
+        });
+        assert!(connect_request(&mut client, "10.0.0.1", 22).await.is_err());
+    }
+}
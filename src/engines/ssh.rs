@@ -1,14 +1,114 @@
-use crate::config_observer::{REMOTE_AUTHORIZED_KEYS, REMOTE_SSH_DIR, SshHost, expand_tilde};
+use crate::config_observer::{
+    AddressFamily, REMOTE_AUTHORIZED_KEYS, REMOTE_SSH_DIR, SshHost, expand_tilde,
+    get_keyring_passphrase, get_keyring_password, is_private_key_encrypted, load_app_config,
+    load_certificate, resolve_algorithm_list, resolve_jump_hosts, store_keyring_passphrase,
+};
+use crate::engines::auth_prompt::{InteractivePrompter, ask_passphrase_blocking};
+use crate::engines::known_hosts::verify_host_key;
+use crate::engines::proxy::{connect_via_proxy, spawn_proxy_command};
+use crate::engines::relay::{spawn_channel_bridge, would_block};
+use crate::engines::sftp::forget_sftp_session;
 use anyhow::Context;
-use ssh2::Session;
+use ssh2::{MethodType, Session};
 use std::collections::HashMap;
 use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::net::{TcpStream, lookup_host};
-use tracing::{info, instrument, warn};
+use tracing::{debug, info, instrument, warn};
 
-type SharedSession = Arc<tokio::sync::Mutex<Option<Session>>>;
+/// A pool entry: the session (if connected) and when it was last handed out.
+struct PoolSlot {
+    session: tokio::sync::Mutex<Option<Session>>,
+    last_used: Mutex<Instant>,
+    /// Held while a channel is being opened on a non-blocking session.
+    channel_open: Arc<Mutex<()>>,
+    /// Operations running on the session; see [`SessionInUse`].
+    in_flight: AtomicUsize,
+}
+
+impl PoolSlot {
+    fn touch(&self) {
+        if let Ok(mut last_used) = self.last_used.lock() {
+            *last_used = Instant::now();
+        }
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_used
+            .lock()
+            .map(|last_used| last_used.elapsed())
+            .unwrap_or_default()
+    }
+
+    fn in_use(&self) -> bool {
+        self.in_flight.load(Ordering::Acquire) > 0
+    }
+}
+
+/// Keeps a pooled session from being closed as idle for as long as it lives, so
+/// a quiet shell, a long transfer or a silent `--follow` stream isn't cut off.
+/// Dropping it counts as a use of the session.
+pub(crate) struct SessionInUse(Option<SharedSession>);
+
+impl SessionInUse {
+    fn new(key: &str) -> Self {
+        let slot = get_ssh_pool()
+            .lock()
+            .ok()
+            .and_then(|pool| pool.get(key).cloned());
+        if let Some(slot) = &slot {
+            slot.in_flight.fetch_add(1, Ordering::AcqRel);
+            slot.touch();
+        }
+        Self(slot)
+    }
+}
+
+impl Drop for SessionInUse {
+    fn drop(&mut self) {
+        if let Some(slot) = self.0.take() {
+            slot.touch();
+            slot.in_flight.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+type SharedSession = Arc<PoolSlot>;
+
+/// The non-blocking session used by [`crate::engines::exec`], with the lock its
+/// users take turns on to open channels (libssh2 handles one pending channel
+/// open per session). The lock stays with the session even if its pool slot is
+/// evicted or reconnected meanwhile.
+pub struct ExecSession {
+    pub session: Session,
+    pub(crate) channel_open: Arc<Mutex<()>>,
+}
+
+/// Seconds between keepalive messages on pooled sessions.
+const KEEPALIVE_INTERVAL: u32 = 30;
+/// How often the maintenance task pings pooled sessions and evicts idle ones.
+const MAINTENANCE_PERIOD: Duration = Duration::from_secs(30);
+/// Sessions unused for this long are closed.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+/// Upper bound on pooled connections; the least recently used idle one makes room.
+const MAX_POOL_SIZE: usize = 16;
+/// Suffix of the pool slots owned by the port forwarders, which keep their own session alive.
+const FORWARD_SUFFIX: &str = "#forward";
+/// Pool key suffix of the non-blocking session used by [`crate::engines::exec`].
+const EXEC_SUFFIX: &str = "#exec";
+
+/// TCP connect and handshake timeout when the host sets no `ConnectTimeout`.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many times the user is asked for a key passphrase before giving up on the key.
+const MAX_PASSPHRASE_ATTEMPTS: usize = 3;
+/// `LIBSSH2_ERROR_FILE`: the private key could not be decrypted or parsed.
+const LIBSSH2_ERROR_FILE: i32 = -16;
 
 /// Restores the previous `SSH_AUTH_SOCK` value when dropped, so temporarily
 /// pointing libssh2 at a host-specific `IdentityAgent` socket doesn't leak.
@@ -33,61 +133,49 @@ fn get_ssh_pool() -> &'static Mutex<HashMap<String, SharedSession>> {
     POOL.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
-/// Establishes an SSH session with the given host, attempting multiple authentication methods.
-/// Reuses an existing session if available and healthy.
-#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias))]
-pub async fn establish_ssh_session(
-    host: &SshHost,
-    password: Option<&str>,
-) -> anyhow::Result<Session> {
-    let host_key = format!(
-        "{}@{}",
-        host.user.as_deref().unwrap_or("root"),
-        host.hostname
-    );
-
-    let shared_session = {
-        let mut pool = get_ssh_pool()
-            .lock()
-            .map_err(|_| anyhow::anyhow!("SSH Pool lock poisoned"))?;
-        pool.entry(host_key)
-            .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(None)))
-            .clone()
-    };
-
-    let mut guard = shared_session.lock().await;
-
-    if let Some(sess) = &*guard {
-        tracing::trace!("Checking existing SSH session health");
-        if sess.authenticated() {
-            // Quick check: try to open a channel
-            let sess_clone = sess.clone();
-            let is_alive =
-                tokio::task::spawn_blocking(move || sess_clone.channel_session().is_ok())
-                    .await
-                    .unwrap_or(false);
-
-            if is_alive {
-                tracing::trace!("Reusing existing SSH session");
-                return Ok(sess.clone());
-            }
-        }
-        tracing::trace!("Existing SSH session stale, reconnecting");
-    }
+/// The host's `ConnectTimeout`, or the default one.
+pub(crate) fn connect_timeout(host: &SshHost) -> Duration {
+    host.connect_timeout
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_CONNECT_TIMEOUT)
+}
 
+/// Resolves the host and opens a TCP connection to the first reachable address
+/// allowed by its `AddressFamily`, or goes through the app-wide outbound proxy
+/// when one is configured.
+async fn connect_tcp(host: &SshHost) -> anyhow::Result<std::net::TcpStream> {
     let port = host.port.unwrap_or(22);
     let addr_str = format!("{}:{}", host.hostname, port);
+    let timeout = connect_timeout(host);
+
+    let proxy = load_app_config()
+        .map(|config| config.outbound_proxy)
+        .unwrap_or_default();
+    if proxy.is_enabled() {
+        let tcp = connect_via_proxy(&proxy, &host.hostname, port, timeout).await?;
+        let std_tcp = tcp.into_std()?;
+        std_tcp.set_nonblocking(false)?;
+        return Ok(std_tcp);
+    }
 
     tracing::trace!("Resolving address {}", addr_str);
-    let addrs = lookup_host(&addr_str)
+    let addrs: Vec<_> = lookup_host(&addr_str)
         .await
-        .with_context(|| format!("Failed to resolve {}", addr_str))?;
+        .with_context(|| format!("Failed to resolve {}", addr_str))?
+        .filter(|addr| host.address_family.is_none_or(|family| family.allows(addr)))
+        .collect();
+    if addrs.is_empty() {
+        anyhow::bail!(
+            "{} has no address matching AddressFamily {:?}",
+            host.hostname,
+            host.address_family.unwrap_or(AddressFamily::Any)
+        );
+    }
 
     let mut tcp_opt = None;
     for addr in addrs {
         tracing::trace!("Attempting TCP connection to {}", addr);
-        if let Ok(stream) =
-            tokio::time::timeout(Duration::from_secs(5), TcpStream::connect(&addr)).await
+        if let Ok(stream) = tokio::time::timeout(timeout, TcpStream::connect(&addr)).await
             && let Ok(stream) = stream
         {
             tracing::trace!("TCP connection established to {}", addr);
@@ -99,99 +187,774 @@ pub async fn establish_ssh_session(
     let tcp = tcp_opt.ok_or_else(|| anyhow::anyhow!("Connection timeout to {}", host.hostname))?;
     let std_tcp = tcp.into_std()?;
     std_tcp.set_nonblocking(false)?;
+    Ok(std_tcp)
+}
 
-    let host_cloned = host.clone();
-    // Keep the in-flight password copy in a buffer that is zeroed on drop.
-    let password_cloned = password.map(|s| zeroize::Zeroizing::new(s.to_string()));
-    let sess = tokio::task::spawn_blocking(move || -> anyhow::Result<Session> {
+/// Opens the transport to a host without going through jump hosts: its
+/// `ProxyCommand` when it has one, a TCP connection otherwise.
+pub(crate) async fn open_direct_transport(host: &SshHost) -> anyhow::Result<OwnedFd> {
+    match &host.proxy_command {
+        Some(command) => Ok(spawn_proxy_command(host, command)?.into()),
+        None => Ok(connect_tcp(host).await?.into()),
+    }
+}
+
+/// Applies the host's `Compression` and algorithm directives to a session that
+/// has not done its handshake yet.
+fn apply_session_options(sess: &Session, host: &SshHost) -> anyhow::Result<()> {
+    if let Some(compress) = host.compression {
+        sess.set_compress(compress);
+    }
+    let prefs = [
+        (
+            "KexAlgorithms",
+            &host.kex_algorithms,
+            &[MethodType::Kex][..],
+        ),
+        (
+            "HostKeyAlgorithms",
+            &host.host_key_algorithms,
+            &[MethodType::HostKey][..],
+        ),
+        (
+            "Ciphers",
+            &host.ciphers,
+            &[MethodType::CryptCs, MethodType::CryptSc][..],
+        ),
+        (
+            "MACs",
+            &host.macs,
+            &[MethodType::MacCs, MethodType::MacSc][..],
+        ),
+    ];
+    for (keyword, spec, methods) in prefs {
+        let Some(spec) = spec else { continue };
+        for &method in methods {
+            let supported = sess
+                .supported_algs(method)
+                .with_context(|| format!("Failed to list supported {}", keyword))?;
+            let algorithms = resolve_algorithm_list(spec, &supported);
+            if algorithms.is_empty() {
+                anyhow::bail!("None of the {} \"{}\" are supported", keyword, spec);
+            }
+            sess.method_pref(method, &algorithms.join(","))
+                .with_context(|| format!("Invalid {} \"{}\"", keyword, spec))?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs the SSH handshake over an already connected, blocking transport, using
+/// the host's algorithm preferences and `ConnectTimeout`.
+pub(crate) async fn handshake<S: AsRawFd + Send + 'static>(
+    stream: S,
+    host: &SshHost,
+) -> anyhow::Result<Session> {
+    let host = host.clone();
+    tokio::task::spawn_blocking(move || -> anyhow::Result<Session> {
         tracing::trace!("Starting SSH handshake");
         let mut sess = Session::new().context("Failed to create SSH session object")?;
-        sess.set_tcp_stream(std_tcp);
+        apply_session_options(&sess, &host)?;
+        sess.set_tcp_stream(stream);
+        sess.set_timeout(connect_timeout(&host).as_millis() as u32);
         sess.handshake()
             .context("SSH handshake failed - check network or firewall")?;
+        sess.set_timeout(0);
+        Ok(sess)
+    })
+    .await?
+}
 
-        let user = host_cloned.user.as_deref().unwrap_or("root");
-        let mut authenticated = false;
-        let mut auth_errors = Vec::new();
-
-        tracing::trace!("Attempting authentication for user: {}", user);
-        if let Some(ref key_path) = host_cloned.identity_file {
-            tracing::trace!("Trying public key authentication: {}", key_path);
-            let path = expand_tilde(key_path);
-            match sess.userauth_pubkey_file(user, None, &path, None) {
-                Ok(_) => {
-                    info!("Authenticated via public key: {}", key_path);
-                    authenticated = true;
-                }
-                Err(e) => {
-                    warn!("Key auth failed: {}", e);
-                    auth_errors.push(format!("Key auth failed: {}", e));
+/// Connects to each `ProxyJump` hop in turn, authenticating on every bastion, and
+/// returns a local socket carrying a `direct-tcpip` channel to the final host.
+pub(crate) async fn open_jump_chain(host: &SshHost) -> anyhow::Result<UnixStream> {
+    let hops = resolve_jump_hosts(host);
+    let mut upstream: Option<Session> = None;
+
+    for hop in &hops {
+        info!("Connecting to jump host {}", hop.alias);
+        let sess = match upstream.take() {
+            None => handshake(open_direct_transport(hop).await?, hop).await?,
+            Some(prev) => handshake(open_direct_tcpip(prev, hop).await?, hop).await?,
+        };
+        verify_host_key(&sess, hop)
+            .await
+            .with_context(|| format!("Jump host {} rejected", hop.alias))?;
+        let hop_password = get_keyring_password(&hop.alias)
+            .await
+            .map(zeroize::Zeroizing::new);
+        let sess = authenticate(sess, hop, hop_password.as_deref().map(String::as_str))
+            .await
+            .with_context(|| format!("Failed to authenticate on jump host {}", hop.alias))?;
+        upstream = Some(sess);
+    }
+
+    let last = upstream.ok_or_else(|| anyhow::anyhow!("Empty ProxyJump chain"))?;
+    open_direct_tcpip(last, host).await
+}
+
+/// Opens a `direct-tcpip` channel from `sess` to `target` and bridges it to a
+/// local socket. The session is handed over to the bridge and must not be shared.
+async fn open_direct_tcpip(sess: Session, target: &SshHost) -> anyhow::Result<UnixStream> {
+    let hostname = target.hostname.clone();
+    let port = target.port.unwrap_or(22);
+    tokio::task::spawn_blocking(move || -> anyhow::Result<UnixStream> {
+        tracing::trace!("Opening direct-tcpip channel to {}:{}", hostname, port);
+        let channel = sess
+            .channel_direct_tcpip(&hostname, port, None)
+            .with_context(|| format!("Jump host could not reach {}:{}", hostname, port))?;
+        spawn_channel_bridge(sess, channel)
+    })
+    .await?
+}
+
+/// Opens the transport to the host (through its `ProxyJump` chain when it has one,
+/// which takes precedence over `ProxyCommand`), performs the SSH handshake and
+/// verifies the server's host key against `known_hosts`. The returned session is
+/// not authenticated yet.
+async fn open_verified_transport(host: &SshHost) -> anyhow::Result<Session> {
+    let sess = if host.proxy_jump.is_empty() {
+        handshake(open_direct_transport(host).await?, host).await?
+    } else {
+        handshake(open_jump_chain(host).await?, host).await?
+    };
+
+    verify_host_key(&sess, host).await?;
+    Ok(sess)
+}
+
+/// Checks the host key of `host` without authenticating, prompting the user
+/// for unknown keys. Used before handing the connection to the system `ssh`
+/// so the terminal follows the same trust rules as the engines.
+#[instrument(fields(host = %host.hostname, alias = %host.alias))]
+pub async fn verify_host(host: &SshHost) -> anyhow::Result<()> {
+    let sess = open_verified_transport(host).await?;
+    let _ =
+        tokio::task::spawn_blocking(move || sess.disconnect(None, "Host key verified", None)).await;
+    Ok(())
+}
+
+/// Outcome of authenticating with an encrypted identity file.
+struct EncryptedKeyAuth {
+    authenticated: bool,
+    /// Passphrase the user asked to remember, to be stored once authenticated.
+    remember: Option<zeroize::Zeroizing<String>>,
+    error: Option<String>,
+}
+
+/// Tries an encrypted identity file with the stored passphrase first, then asks
+/// the user until the key decrypts or they give up. Blocking.
+fn auth_with_encrypted_key(
+    sess: &Session,
+    host: &SshHost,
+    user: &str,
+    key_path: &str,
+    certificate: Option<&Path>,
+    mut stored: Option<zeroize::Zeroizing<String>>,
+) -> EncryptedKeyAuth {
+    let path = expand_tilde(key_path);
+    let mut asked = 0;
+    loop {
+        let (passphrase, remember) = match stored.take() {
+            Some(passphrase) => (passphrase, false),
+            None if asked < MAX_PASSPHRASE_ATTEMPTS => {
+                asked += 1;
+                match ask_passphrase_blocking(&host.alias, key_path, asked > 1) {
+                    Some(answer) => answer,
+                    None => {
+                        return EncryptedKeyAuth {
+                            authenticated: false,
+                            remember: None,
+                            error: Some("Key passphrase prompt cancelled".to_string()),
+                        };
+                    }
                 }
             }
+            None => {
+                return EncryptedKeyAuth {
+                    authenticated: false,
+                    remember: None,
+                    error: Some("Wrong key passphrase".to_string()),
+                };
+            }
+        };
+
+        match sess.userauth_pubkey_file(user, certificate, &path, Some(&passphrase)) {
+            Ok(_) => {
+                info!("Authenticated via encrypted public key: {}", key_path);
+                return EncryptedKeyAuth {
+                    authenticated: true,
+                    remember: remember.then_some(passphrase),
+                    error: None,
+                };
+            }
+            Err(e) if e.code() == ssh2::ErrorCode::Session(LIBSSH2_ERROR_FILE) => {
+                warn!("Could not decrypt {}: {}", key_path, e);
+            }
+            Err(e) => {
+                // The key decrypted but the server refused it: asking again won't help.
+                warn!("Key auth failed: {}", e);
+                return EncryptedKeyAuth {
+                    authenticated: false,
+                    remember: None,
+                    error: Some(format!("Key auth failed: {}", e)),
+                };
+            }
         }
+    }
+}
 
-        if !authenticated {
-            // libssh2 (via the ssh2 crate) only discovers the agent socket
-            // through `SSH_AUTH_SOCK`. When the host declares an `IdentityAgent`
-            // in the SSH config (e.g. 1Password's `~/.1password/agent.sock`),
-            // point the agent at that socket so it behaves like the system
-            // `ssh` client used by the terminal.
-            let _agent_sock_guard = host_cloned.identity_agent.as_deref().map(|agent_path| {
-                let expanded = expand_tilde(agent_path);
-                let previous = std::env::var_os("SSH_AUTH_SOCK");
-                tracing::trace!("Using IdentityAgent socket: {}", expanded.display());
-                // SAFETY: SSH auth runs serially within this blocking task and
-                // the previous value is restored when the guard drops.
-                unsafe {
-                    std::env::set_var("SSH_AUTH_SOCK", &expanded);
-                }
-                SshAuthSockGuard { previous }
-            });
+/// One authentication method tried against a server, for diagnostics.
+#[derive(Debug, Clone)]
+pub struct AuthAttempt {
+    /// Method and credential, e.g. `publickey (~/.ssh/id_ed25519)`.
+    pub method: String,
+    /// `None` when the method succeeded.
+    pub error: Option<String>,
+}
 
-            tracing::trace!("Trying agent authentication");
-            if sess.userauth_agent(user).is_ok() {
-                info!("Authenticated via SSH agent");
-                authenticated = true;
-            } else {
-                tracing::trace!("Agent auth failed or no agent running");
-                auth_errors.push("Agent auth failed or no agent running".to_string());
+impl AuthAttempt {
+    fn succeeded(method: impl Into<String>) -> Self {
+        Self {
+            method: method.into(),
+            error: None,
+        }
+    }
+
+    fn failed(method: impl Into<String>, error: impl Into<String>) -> Self {
+        Self {
+            method: method.into(),
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// Authenticates a handshaken session, trying the identity file, the agent, the
+/// password and finally keyboard-interactive, skipping methods the server doesn't offer.
+async fn authenticate(
+    sess: Session,
+    host: &SshHost,
+    password: Option<&str>,
+) -> anyhow::Result<Session> {
+    try_authenticate(sess, host, password).await.0
+}
+
+/// Same as [`authenticate`], also returning every method that was tried.
+pub(crate) async fn try_authenticate(
+    sess: Session,
+    host: &SshHost,
+    password: Option<&str>,
+) -> (anyhow::Result<Session>, Vec<AuthAttempt>) {
+    let host_cloned = host.clone();
+    // Keep the in-flight password copy in a buffer that is zeroed on drop.
+    let password_cloned = password.map(|s| zeroize::Zeroizing::new(s.to_string()));
+    let identity_key = host.identity_key();
+    let key_encrypted = identity_key.as_deref().is_some_and(|key_path| {
+        std::fs::read_to_string(expand_tilde(key_path))
+            .is_ok_and(|content| is_private_key_encrypted(&content))
+    });
+    let stored_passphrase = match (&identity_key, key_encrypted) {
+        (Some(key_path), true) => get_keyring_passphrase(key_path)
+            .await
+            .map(zeroize::Zeroizing::new),
+        _ => None,
+    };
+    let joined = tokio::task::spawn_blocking(move || {
+        let mut attempts = Vec::new();
+        let result = run_auth_methods(
+            &sess,
+            &host_cloned,
+            password_cloned,
+            key_encrypted,
+            stored_passphrase,
+            &mut attempts,
+        );
+        (result.map(|remember| (sess, remember)), attempts)
+    })
+    .await;
+    let (result, attempts) = match joined {
+        Ok(outcome) => outcome,
+        Err(e) => return (Err(e.into()), Vec::new()),
+    };
+    let (sess, remember_passphrase) = match result {
+        Ok(authenticated) => authenticated,
+        Err(e) => return (Err(e), attempts),
+    };
+
+    if let (Some(passphrase), Some(key_path)) = (remember_passphrase, &identity_key)
+        && let Err(e) = store_keyring_passphrase(key_path, &passphrase).await
+    {
+        warn!("Failed to store key passphrase in keyring: {}", e);
+    }
+    (Ok(sess), attempts)
+}
+
+/// Runs the authentication methods in order until one succeeds, recording each
+/// attempt. Returns the key passphrase the user asked to remember, if any. Blocking.
+fn run_auth_methods(
+    sess: &Session,
+    host: &SshHost,
+    password: Option<zeroize::Zeroizing<String>>,
+    key_encrypted: bool,
+    stored_passphrase: Option<zeroize::Zeroizing<String>>,
+    attempts: &mut Vec<AuthAttempt>,
+) -> anyhow::Result<Option<zeroize::Zeroizing<String>>> {
+    let user = host.user.as_deref().unwrap_or("root");
+
+    tracing::trace!("Attempting authentication for user: {}", user);
+    // Asking for the methods sends a "none" request, which some servers accept.
+    let methods = match sess.auth_methods(user) {
+        Ok(methods) => methods.to_string(),
+        Err(_) if sess.authenticated() => {
+            info!("Authenticated without credentials");
+            attempts.push(AuthAttempt::succeeded("none"));
+            return Ok(None);
+        }
+        Err(e) => return Err(e).context("Failed to query authentication methods"),
+    };
+    tracing::trace!("Server offers authentication methods: {}", methods);
+    let offers = |method: &str| methods.split(',').any(|m| m == method);
+    let mut authenticated = false;
+    let mut remember_passphrase = None;
+
+    let identity_key = host.identity_key();
+    let certificate = host.certificate_file.as_deref().map(expand_tilde);
+    if let Some(ref cert_path) = certificate {
+        match load_certificate(cert_path) {
+            Ok(cert) if cert.is_expired() => {
+                warn!("Certificate {} has expired", cert_path.display())
             }
+            Ok(_) => {}
+            Err(e) => warn!("{:#}", e),
         }
+    }
+    let key_method = |key_path: &str| match &host.certificate_file {
+        Some(cert) => format!("publickey ({} with certificate {})", key_path, cert),
+        None => format!("publickey ({})", key_path),
+    };
 
-        if !authenticated && let Some(ref pass) = password_cloned {
-            tracing::trace!("Trying password authentication");
-            match sess.userauth_password(user, pass) {
-                Ok(_) => {
-                    info!("Authenticated via password");
-                    authenticated = true;
-                }
-                Err(e) => {
-                    warn!("Password auth failed: {}", e);
-                    auth_errors.push(format!("Password auth failed: {}", e));
-                }
+    if !offers("publickey") {
+        tracing::trace!("Server does not offer publickey, skipping keys and agent");
+    } else if let Some(ref key_path) = identity_key
+        && key_encrypted
+    {
+        tracing::trace!("Trying encrypted public key authentication: {}", key_path);
+        let outcome = auth_with_encrypted_key(
+            sess,
+            host,
+            user,
+            key_path,
+            certificate.as_deref(),
+            stored_passphrase,
+        );
+        authenticated = outcome.authenticated;
+        remember_passphrase = outcome.remember;
+        let method = key_method(key_path);
+        attempts.push(match outcome.error {
+            Some(error) => AuthAttempt::failed(method, error),
+            None => AuthAttempt::succeeded(method),
+        });
+    } else if let Some(ref key_path) = identity_key {
+        tracing::trace!("Trying public key authentication: {}", key_path);
+        let path = expand_tilde(key_path);
+        let method = key_method(key_path);
+        match sess.userauth_pubkey_file(user, certificate.as_deref(), &path, None) {
+            Ok(_) => {
+                info!("Authenticated via public key: {}", key_path);
+                authenticated = true;
+                attempts.push(AuthAttempt::succeeded(method));
+            }
+            Err(e) => {
+                warn!("Key auth failed: {}", e);
+                attempts.push(AuthAttempt::failed(
+                    method,
+                    format!("Key auth failed: {}", e),
+                ));
             }
         }
+    }
 
-        if authenticated {
-            Ok(sess)
+    if !authenticated && offers("publickey") {
+        // libssh2 (via the ssh2 crate) only discovers the agent socket
+        // through `SSH_AUTH_SOCK`. When the host declares an `IdentityAgent`
+        // in the SSH config (e.g. 1Password's `~/.1password/agent.sock`),
+        // point the agent at that socket so it behaves like the system
+        // `ssh` client used by the terminal.
+        let _agent_sock_guard = host.identity_agent.as_deref().map(|agent_path| {
+            let expanded = expand_tilde(agent_path);
+            let previous = std::env::var_os("SSH_AUTH_SOCK");
+            tracing::trace!("Using IdentityAgent socket: {}", expanded.display());
+            // SAFETY: SSH auth runs serially within this blocking task and
+            // the previous value is restored when the guard drops.
+            unsafe {
+                std::env::set_var("SSH_AUTH_SOCK", &expanded);
+            }
+            SshAuthSockGuard { previous }
+        });
+
+        tracing::trace!("Trying agent authentication");
+        if sess.userauth_agent(user).is_ok() {
+            info!("Authenticated via SSH agent");
+            authenticated = true;
+            attempts.push(AuthAttempt::succeeded("agent"));
         } else {
-            let combined = auth_errors.join("; ");
+            tracing::trace!("Agent auth failed or no agent running");
+            attempts.push(AuthAttempt::failed(
+                "agent",
+                "Agent auth failed or no agent running",
+            ));
+        }
+    }
+
+    if !authenticated
+        && offers("password")
+        && let Some(ref pass) = password
+    {
+        tracing::trace!("Trying password authentication");
+        match sess.userauth_password(user, pass) {
+            Ok(_) => {
+                info!("Authenticated via password");
+                authenticated = true;
+                attempts.push(AuthAttempt::succeeded("password"));
+            }
+            Err(e) => {
+                warn!("Password auth failed: {}", e);
+                attempts.push(AuthAttempt::failed(
+                    "password",
+                    format!("Password auth failed: {}", e),
+                ));
+            }
+        }
+    }
+
+    if !authenticated && offers("keyboard-interactive") {
+        tracing::trace!("Trying keyboard-interactive authentication");
+        let mut prompter = InteractivePrompter::new(&host.alias, password.clone());
+        match sess.userauth_keyboard_interactive(user, &mut prompter) {
+            Ok(_) => {
+                info!("Authenticated via keyboard-interactive");
+                authenticated = true;
+                attempts.push(AuthAttempt::succeeded("keyboard-interactive"));
+            }
+            Err(_) if prompter.cancelled => {
+                attempts.push(AuthAttempt::failed("keyboard-interactive", "Cancelled"));
+                anyhow::bail!(
+                    "Authentication for {}@{} was cancelled",
+                    user,
+                    host.hostname
+                );
+            }
+            Err(e) => {
+                warn!("Keyboard-interactive auth failed: {}", e);
+                attempts.push(AuthAttempt::failed(
+                    "keyboard-interactive",
+                    format!("Keyboard-interactive auth failed: {}", e),
+                ));
+            }
+        }
+    }
+
+    if authenticated {
+        return Ok(remember_passphrase);
+    }
+    let combined = attempts
+        .iter()
+        .filter_map(|attempt| attempt.error.as_deref())
+        .collect::<Vec<_>>()
+        .join("; ");
+    warn!(
+        "Authentication failed for {}@{}: {}",
+        user, host.hostname, combined
+    );
+    Err(anyhow::anyhow!(
+        "Authentication failed for {}@{}. Details: {}",
+        user,
+        host.hostname,
+        combined
+    ))
+}
+
+/// Pool key of the command/SFTP session of a host.
+pub(crate) fn pool_key(host: &SshHost) -> String {
+    format!(
+        "{}@{}",
+        host.user.as_deref().unwrap_or("root"),
+        host.hostname
+    )
+}
+
+/// Marks the pooled session of a host as in use, so idle eviction leaves it alone
+/// while another pool (SFTP) keeps working on it.
+pub(crate) fn touch_pooled_session(host: &SshHost) {
+    touch_slot(&pool_key(host));
+}
+
+/// Marks the pooled session of a host as busy until the returned guard is dropped.
+pub(crate) fn use_pooled_session(host: &SshHost) -> SessionInUse {
+    SessionInUse::new(&pool_key(host))
+}
+
+/// Same as [`use_pooled_session`] for the command execution session.
+pub(crate) fn use_exec_session(host: &SshHost) -> SessionInUse {
+    SessionInUse::new(&format!("{}{}", pool_key(host), EXEC_SUFFIX))
+}
+
+/// Same as [`touch_pooled_session`] for the command execution session.
+pub(crate) fn touch_exec_session(host: &SshHost) {
+    touch_slot(&format!("{}{}", pool_key(host), EXEC_SUFFIX));
+}
+
+fn touch_slot(key: &str) {
+    if let Ok(pool) = get_ssh_pool().lock()
+        && let Some(slot) = pool.get(key)
+    {
+        slot.touch();
+    }
+}
+
+/// Drops the pooled session of a host so the next operation reconnects.
+pub(crate) async fn invalidate_pooled_session(host: &SshHost) {
+    invalidate_slot(&pool_key(host)).await;
+}
+
+/// Drops the command execution session of a host so the next command reconnects.
+pub(crate) async fn invalidate_exec_session(host: &SshHost) {
+    invalidate_slot(&format!("{}{}", pool_key(host), EXEC_SUFFIX)).await;
+}
+
+/// Drops the forwarding session of a host so the forwarder reconnects.
+pub(crate) async fn invalidate_forwarding_session(host: &SshHost) {
+    invalidate_slot(&format!("{}{}", pool_key(host), FORWARD_SUFFIX)).await;
+}
+
+async fn invalidate_slot(key: &str) {
+    let slot = get_ssh_pool()
+        .lock()
+        .ok()
+        .and_then(|pool| pool.get(key).cloned());
+    if let Some(slot) = slot {
+        *slot.session.lock().await = None;
+    }
+}
+
+/// Returns `true` when an error means the transport is gone (rather than, say, a
+/// missing file), i.e. when reconnecting and retrying is worthwhile.
+pub(crate) fn is_connection_error(e: &anyhow::Error) -> bool {
+    // LIBSSH2_ERROR_SOCKET_SEND, _TIMEOUT, _SOCKET_DISCONNECT, _SOCKET_TIMEOUT, _SOCKET_RECV
+    const TRANSPORT_ERRORS: [i32; 5] = [-7, -9, -13, -30, -43];
+    let is_transport = |err: &ssh2::Error| matches!(err.code(), ssh2::ErrorCode::Session(code) if TRANSPORT_ERRORS.contains(&code));
+    e.chain().any(|cause| {
+        if let Some(err) = cause.downcast_ref::<ssh2::Error>() {
+            return is_transport(err);
+        }
+        if let Some(err) = cause.downcast_ref::<std::io::Error>() {
+            return matches!(
+                err.kind(),
+                std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::UnexpectedEof
+                    | std::io::ErrorKind::TimedOut
+            ) || err
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<ssh2::Error>())
+                .is_some_and(is_transport);
+        }
+        false
+    })
+}
+
+/// Starts (once) the background task that sends keepalives on pooled sessions,
+/// drops the ones that stopped answering and closes idle ones.
+fn ensure_pool_maintenance() {
+    static STARTED: OnceLock<()> = OnceLock::new();
+    STARTED.get_or_init(|| {
+        tokio::spawn(async {
+            let mut interval = tokio::time::interval(MAINTENANCE_PERIOD);
+            loop {
+                interval.tick().await;
+                maintain_pool().await;
+            }
+        });
+    });
+}
+
+async fn maintain_pool() {
+    let slots: Vec<(String, SharedSession)> = match get_ssh_pool().lock() {
+        Ok(pool) => pool
+            .iter()
+            .filter(|(key, _)| !key.ends_with(FORWARD_SUFFIX))
+            .map(|(key, slot)| (key.clone(), slot.clone()))
+            .collect(),
+        Err(_) => return,
+    };
+
+    for (key, slot) in slots {
+        // A slot that is locked is being connected right now.
+        let Ok(mut guard) = slot.session.try_lock() else {
+            continue;
+        };
+        let Some(sess) = guard.clone() else {
+            continue;
+        };
+        if !slot.in_use() && slot.idle_for() >= IDLE_TIMEOUT {
+            debug!("Closing idle SSH session {}", key);
+            *guard = None;
+            forget_sftp_session(&key);
+            drop(guard);
+            let _ =
+                tokio::task::spawn_blocking(move || sess.disconnect(None, "Idle timeout", None))
+                    .await;
+            continue;
+        }
+        let alive = tokio::task::spawn_blocking(move || is_session_alive(&sess))
+            .await
+            .unwrap_or(false);
+        if !alive {
             warn!(
-                "Authentication failed for {}@{}: {}",
-                user, host_cloned.hostname, combined
+                "SSH session {} stopped answering keepalives, dropping it",
+                key
             );
-            Err(anyhow::anyhow!(
-                "Authentication failed for {}@{}. Details: {}",
-                user,
-                host_cloned.hostname,
-                combined
-            ))
+            *guard = None;
+            forget_sftp_session(&key);
         }
+    }
+}
+
+/// Probes a pooled session. Non-blocking sessions (command execution) cannot open a
+/// probe channel without racing their users, so they only get a keepalive.
+fn is_session_alive(sess: &Session) -> bool {
+    match sess.keepalive_send() {
+        Ok(_) => true,
+        Err(e) => !sess.is_blocking() && would_block(&e),
+    }
+}
+
+/// Makes room for a new pool entry by evicting the least recently used idle
+/// session once the pool is full.
+fn evict_for_new_entry(pool: &mut HashMap<String, SharedSession>) {
+    if pool.len() < MAX_POOL_SIZE {
+        return;
+    }
+    let victim = pool
+        .iter()
+        .filter(|(key, slot)| {
+            !key.ends_with(FORWARD_SUFFIX) && !slot.in_use() && slot.session.try_lock().is_ok()
+        })
+        .max_by_key(|(_, slot)| slot.idle_for())
+        .map(|(key, _)| key.clone());
+    if let Some(key) = victim {
+        debug!("SSH pool full, evicting {}", key);
+        pool.remove(&key);
+        forget_sftp_session(&key);
+    }
+}
+
+/// Establishes an SSH session with the given host, attempting multiple authentication methods.
+/// Reuses an existing session if available and healthy.
+#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias))]
+pub async fn establish_ssh_session(
+    host: &SshHost,
+    password: Option<&str>,
+) -> anyhow::Result<Session> {
+    establish_pooled_session(host, password, pool_key(host)).await
+}
+
+/// Establishes the pooled session used for port forwarding. Forwarding drives its
+/// session in non-blocking mode from a dedicated I/O thread (a blocking channel
+/// read would hold libssh2's session lock until data arrives), so it gets its own
+/// pool slot instead of sharing the connection used by commands and SFTP.
+#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias))]
+pub async fn establish_forwarding_session(
+    host: &SshHost,
+    password: Option<&str>,
+) -> anyhow::Result<Session> {
+    let host_key = format!("{}{}", pool_key(host), FORWARD_SUFFIX);
+    establish_pooled_session(host, password, host_key).await
+}
+
+/// Establishes the pooled session used by [`crate::engines::exec`]. Commands are
+/// driven in non-blocking mode so that slow or endless ones (`tail -f`) can be
+/// timed out or cancelled without stalling SFTP on the shared session.
+#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias))]
+pub async fn establish_exec_session(
+    host: &SshHost,
+    password: Option<&str>,
+) -> anyhow::Result<ExecSession> {
+    let host_key = format!("{}{}", pool_key(host), EXEC_SUFFIX);
+    let (session, slot) = establish_pooled_slot(host, password, host_key).await?;
+    session.set_blocking(false);
+    Ok(ExecSession {
+        session,
+        channel_open: slot.channel_open.clone(),
     })
-    .await??;
+}
+
+async fn establish_pooled_session(
+    host: &SshHost,
+    password: Option<&str>,
+    host_key: String,
+) -> anyhow::Result<Session> {
+    Ok(establish_pooled_slot(host, password, host_key).await?.0)
+}
+
+async fn establish_pooled_slot(
+    host: &SshHost,
+    password: Option<&str>,
+    host_key: String,
+) -> anyhow::Result<(Session, SharedSession)> {
+    ensure_pool_maintenance();
+    let shared_session = {
+        let mut pool = get_ssh_pool()
+            .lock()
+            .map_err(|_| anyhow::anyhow!("SSH Pool lock poisoned"))?;
+        if !pool.contains_key(&host_key) {
+            evict_for_new_entry(&mut pool);
+        }
+        pool.entry(host_key)
+            .or_insert_with(|| {
+                Arc::new(PoolSlot {
+                    session: tokio::sync::Mutex::new(None),
+                    last_used: Mutex::new(Instant::now()),
+                    channel_open: Arc::default(),
+                    in_flight: AtomicUsize::new(0),
+                })
+            })
+            .clone()
+    };
+    shared_session.touch();
+
+    let mut guard = shared_session.session.lock().await;
+
+    if let Some(sess) = &*guard {
+        tracing::trace!("Checking existing SSH session health");
+        if sess.authenticated() {
+            // Quick check: try to open a channel (blocking sessions) or send a keepalive
+            let sess_clone = sess.clone();
+            let is_alive = tokio::task::spawn_blocking(move || {
+                if sess_clone.is_blocking() {
+                    sess_clone.channel_session().is_ok()
+                } else {
+                    is_session_alive(&sess_clone)
+                }
+            })
+            .await
+            .unwrap_or(false);
+
+            if is_alive {
+                tracing::trace!("Reusing existing SSH session");
+                return Ok((sess.clone(), shared_session.clone()));
+            }
+        }
+        tracing::trace!("Existing SSH session stale, reconnecting");
+    }
+
+    let sess = open_verified_transport(host).await?;
+    let sess = authenticate(sess, host, password).await?;
+    sess.set_keepalive(true, KEEPALIVE_INTERVAL);
 
     *guard = Some(sess.clone());
-    Ok(sess)
+    Ok((sess, shared_session.clone()))
 }
 
 /// Deploys a public key to the remote host's `authorized_keys` file.
@@ -203,9 +966,11 @@ pub async fn deploy_pubkey(
 ) -> anyhow::Result<()> {
     info!("Deploying public key to {}", host.alias);
     let sess = establish_ssh_session(host, password).await?;
+    let in_use = use_pooled_session(host);
     let pubkey_owned = pubkey_content.to_string();
 
     tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+        let _in_use = in_use;
         let mut channel = sess
             .channel_session()
             .context("Failed to open SSH channel for deployment")?;
@@ -248,12 +1013,32 @@ pub async fn run_remote_command(
     command: &str,
 ) -> anyhow::Result<String> {
     tracing::trace!("Running remote command on {}: {}", host.alias, command);
+    match exec_remote_command(host, password, command).await {
+        Err(e) if is_connection_error(&e) => {
+            warn!(
+                "Connection to {} lost ({:#}), reconnecting once",
+                host.alias, e
+            );
+            invalidate_pooled_session(host).await;
+            exec_remote_command(host, password, command).await
+        }
+        result => result,
+    }
+}
+
+async fn exec_remote_command(
+    host: &SshHost,
+    password: Option<&str>,
+    command: &str,
+) -> anyhow::Result<String> {
     let sess = establish_ssh_session(host, password)
         .await
         .context("Failed to establish SSH session for remote command")?;
+    let in_use = use_pooled_session(host);
     let cmd_owned = command.to_string();
 
     tokio::task::spawn_blocking(move || -> anyhow::Result<String> {
+        let _in_use = in_use;
         let mut channel = sess
             .channel_session()
             .context("Failed to open SSH channel for command execution")?;
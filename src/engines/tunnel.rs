@@ -0,0 +1,782 @@
+use crate::config_observer::SshHost;
+use crate::engines::relay::{BUF_SIZE, IdleBackoff, Relay, would_block};
+use crate::engines::socks;
+use crate::engines::ssh::{establish_forwarding_session, invalidate_forwarding_session};
+use anyhow::Context;
+use ssh2::{Channel, Listener, Session};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, oneshot};
+use tracing::{debug, info, instrument, warn};
+
+/// Seconds between keepalives on a forwarding session; a failed keepalive is how
+/// a dead connection is noticed while every tunnel is idle.
+const KEEPALIVE_INTERVAL: u32 = 15;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// Pause before asking the server again for a reverse listener it refused after a reconnect.
+const RELISTEN_DELAY: Duration = Duration::from_secs(5);
+/// Address reverse tunnels listen on, on the server side (same default as `ssh -R`).
+const REMOTE_BIND_ADDRESS: &str = "localhost";
+
+/// What a tunnel forwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TunnelKind {
+    /// `ssh -L`: a local port forwarded to `target_host:target_port` as seen from the server.
+    Local {
+        bind_port: u16,
+        target_host: String,
+        target_port: u16,
+    },
+    /// `ssh -R`: a port on the server forwarded back to `local_host:local_port`.
+    Remote {
+        remote_port: u16,
+        local_host: String,
+        local_port: u16,
+    },
+    /// `ssh -D`: a local SOCKS5 proxy whose connections leave from the server.
+    Dynamic { bind_port: u16 },
+}
+
+impl TunnelKind {
+    /// Short human readable summary, e.g. `localhost:8080 → db:5432`.
+    pub fn describe(&self) -> String {
+        match self {
+            TunnelKind::Local {
+                bind_port,
+                target_host,
+                target_port,
+            } => format!("localhost:{} → {}:{}", bind_port, target_host, target_port),
+            TunnelKind::Remote {
+                remote_port,
+                local_host,
+                local_port,
+            } => format!("remote:{} → {}:{}", remote_port, local_host, local_port),
+            TunnelKind::Dynamic { bind_port } => format!("SOCKS5 proxy on localhost:{}", bind_port),
+        }
+    }
+}
+
+/// A running port forward and its live traffic counters.
+#[derive(Debug)]
+pub struct Tunnel {
+    pub id: u64,
+    pub alias: String,
+    pub kind: TunnelKind,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    connections: AtomicUsize,
+    stopped: AtomicBool,
+    stop_notify: Notify,
+}
+
+impl Tunnel {
+    fn new(alias: &str, kind: TunnelKind) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            alias: alias.to_string(),
+            kind,
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            connections: AtomicUsize::new(0),
+            stopped: AtomicBool::new(false),
+            stop_notify: Notify::new(),
+        }
+    }
+
+    /// Bytes sent from the local side to the server.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Ordering::Relaxed)
+    }
+
+    /// Bytes received from the server.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+
+    /// Number of connections currently relayed through the tunnel.
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    /// Stops accepting connections and closes the ones in flight.
+    pub fn stop(&self) {
+        if self.stopped.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        self.stop_notify.notify_one();
+        if let Ok(mut tunnels) = get_tunnels().lock() {
+            tunnels.retain(|t| t.id != self.id);
+        }
+        info!("Stopped tunnel {} on {}", self.kind.describe(), self.alias);
+    }
+}
+
+static TUNNELS: OnceLock<Mutex<Vec<Arc<Tunnel>>>> = OnceLock::new();
+
+fn get_tunnels() -> &'static Mutex<Vec<Arc<Tunnel>>> {
+    TUNNELS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Returns the active tunnels of a host, oldest first.
+pub fn list_tunnels(alias: &str) -> Vec<Arc<Tunnel>> {
+    get_tunnels()
+        .lock()
+        .map(|tunnels| {
+            tunnels
+                .iter()
+                .filter(|t| t.alias == alias)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn has_active_tunnels(alias: &str) -> bool {
+    get_tunnels()
+        .lock()
+        .map(|tunnels| tunnels.iter().any(|t| t.alias == alias))
+        .unwrap_or(false)
+}
+
+fn register_tunnel(tunnel: Arc<Tunnel>) -> anyhow::Result<()> {
+    get_tunnels()
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Tunnel registry lock poisoned"))?
+        .push(tunnel);
+    Ok(())
+}
+
+/// A local connection waiting for its `direct-tcpip` channel.
+struct PendingConnect {
+    tunnel: Arc<Tunnel>,
+    stream: TcpStream,
+    target_host: String,
+    target_port: u16,
+    /// The client is waiting for a SOCKS5 reply telling whether the channel opened.
+    socks: bool,
+}
+
+impl PendingConnect {
+    fn send_socks_reply(&mut self, code: u8) {
+        if self.socks {
+            // A 10 byte write to a fresh socket doesn't block in practice.
+            let _ = self.stream.write_all(&socks::reply(code));
+        }
+    }
+}
+
+enum ForwarderCommand {
+    /// Open a `direct-tcpip` channel and relay the accepted local connection over it.
+    Connect(PendingConnect),
+    /// Ask the server to listen for a reverse tunnel; the result is reported on `ready`.
+    Listen {
+        tunnel: Arc<Tunnel>,
+        ready: oneshot::Sender<anyhow::Result<()>>,
+    },
+    /// Relay a channel accepted on a remote listener once its local connection is up.
+    Attach {
+        tunnel: Arc<Tunnel>,
+        channel: Channel,
+        stream: std::io::Result<TcpStream>,
+    },
+}
+
+type ForwarderHandle = mpsc::Sender<ForwarderCommand>;
+
+static FORWARDERS: OnceLock<Mutex<HashMap<String, ForwarderHandle>>> = OnceLock::new();
+
+fn get_forwarders() -> &'static Mutex<HashMap<String, ForwarderHandle>> {
+    FORWARDERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Returns the forwarder of a host, starting it (and its SSH session) if needed.
+///
+/// Every tunnel of a host shares one forwarder thread which owns a dedicated,
+/// non-blocking session and multiplexes all channels over it.
+async fn get_forwarder(host: &SshHost, password: Option<&str>) -> anyhow::Result<ForwarderHandle> {
+    if let Some(handle) = get_forwarders()
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Forwarder lock poisoned"))?
+        .get(&host.alias)
+    {
+        return Ok(handle.clone());
+    }
+
+    let sess = establish_forwarding_session(host, password).await?;
+
+    let mut forwarders = get_forwarders()
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Forwarder lock poisoned"))?;
+    if let Some(handle) = forwarders.get(&host.alias) {
+        return Ok(handle.clone());
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let forwarder = Forwarder {
+        host: host.clone(),
+        password: password.map(str::to_string),
+        runtime: tokio::runtime::Handle::current(),
+        sess,
+        tx: tx.clone(),
+        rx,
+        pending: VecDeque::new(),
+        remote: Vec::new(),
+        relays: Vec::new(),
+    };
+    std::thread::Builder::new()
+        .name(format!("ssh-forward-{}", host.alias))
+        .spawn(move || forwarder.run())
+        .context("Failed to spawn forwarding thread")?;
+    forwarders.insert(host.alias.clone(), tx.clone());
+    Ok(tx)
+}
+
+/// Hands a command to the host's forwarder, restarting it if it has just exited.
+async fn send_to_forwarder(
+    host: &SshHost,
+    password: Option<&str>,
+    command: ForwarderCommand,
+) -> anyhow::Result<()> {
+    let handle = get_forwarder(host, password).await?;
+    if let Err(mpsc::SendError(command)) = handle.send(command) {
+        get_forwarder(host, password)
+            .await?
+            .send(command)
+            .map_err(|_| anyhow::anyhow!("Forwarder for {} stopped", host.alias))?;
+    }
+    Ok(())
+}
+
+/// Closes a channel that never got a local side. Best effort, like [`Relay::close`].
+fn close_channel(mut channel: Channel) {
+    let _ = channel.close();
+}
+
+struct ActiveRelay {
+    tunnel: Arc<Tunnel>,
+    relay: Relay<TcpStream>,
+}
+
+/// A reverse tunnel and its listener on the server. The listener is dropped when the
+/// session is lost and requested again once it is back.
+struct RemoteForward {
+    tunnel: Arc<Tunnel>,
+    listener: Option<Listener>,
+    ready: Option<oneshot::Sender<anyhow::Result<()>>>,
+    retry_at: Option<Instant>,
+}
+
+struct Forwarder {
+    host: SshHost,
+    password: Option<String>,
+    runtime: tokio::runtime::Handle,
+    sess: Session,
+    /// Used by local connect tasks to hand accepted remote channels back.
+    tx: ForwarderHandle,
+    rx: mpsc::Receiver<ForwarderCommand>,
+    /// Connections waiting for their channel. libssh2 can only have one channel
+    /// open in progress per session, so they are opened one at a time.
+    pending: VecDeque<PendingConnect>,
+    remote: Vec<RemoteForward>,
+    relays: Vec<ActiveRelay>,
+}
+
+impl Forwarder {
+    fn run(mut self) {
+        info!("Forwarder started for {}", self.host.alias);
+        self.sess.set_keepalive(true, KEEPALIVE_INTERVAL);
+        self.sess.set_blocking(false);
+
+        let mut buf = vec![0u8; BUF_SIZE];
+        let mut backoff = IdleBackoff::new();
+        loop {
+            while let Ok(command) = self.rx.try_recv() {
+                self.handle_command(command);
+            }
+
+            let mut progressed = self.open_pending();
+            progressed |= self.listen_remote();
+            progressed |= self.accept_remote();
+            progressed |= self.pump_relays(&mut buf);
+
+            if let Err(e) = self.sess.keepalive_send()
+                && !would_block(&e)
+            {
+                warn!("Forwarding session to {} lost: {}", self.host.alias, e);
+                if !self.reconnect() {
+                    break;
+                }
+                continue;
+            }
+
+            if self.relays.is_empty() && self.pending.is_empty() && self.should_exit() {
+                break;
+            }
+
+            if progressed {
+                backoff.reset();
+            } else {
+                backoff.sleep();
+            }
+        }
+
+        for active in self.relays.drain(..) {
+            active.tunnel.connections.fetch_sub(1, Ordering::Relaxed);
+            active.relay.close();
+        }
+        self.sess.set_blocking(true);
+        info!("Forwarder stopped for {}", self.host.alias);
+    }
+
+    /// Exits once the host has no tunnel left. Decided under the forwarder lock so
+    /// a tunnel being started concurrently either sees this forwarder or a new one.
+    fn should_exit(&self) -> bool {
+        let Ok(mut forwarders) = get_forwarders().lock() else {
+            return true;
+        };
+        if has_active_tunnels(&self.host.alias) {
+            return false;
+        }
+        forwarders.remove(&self.host.alias);
+        true
+    }
+
+    fn handle_command(&mut self, command: ForwarderCommand) {
+        match command {
+            ForwarderCommand::Connect(pending) => self.pending.push_back(pending),
+            ForwarderCommand::Listen { tunnel, ready } => self.remote.push(RemoteForward {
+                tunnel,
+                listener: None,
+                ready: Some(ready),
+                retry_at: None,
+            }),
+            ForwarderCommand::Attach {
+                tunnel,
+                channel,
+                stream,
+            } => match stream {
+                Ok(stream) if !tunnel.is_stopped() => {
+                    tunnel.connections.fetch_add(1, Ordering::Relaxed);
+                    self.relays.push(ActiveRelay {
+                        tunnel,
+                        relay: Relay::new(channel, stream),
+                    });
+                }
+                Ok(_) => close_channel(channel),
+                Err(e) => {
+                    warn!("Failed to reach {}: {}", tunnel.kind.describe(), e);
+                    close_channel(channel);
+                }
+            },
+        }
+    }
+
+    fn open_pending(&mut self) -> bool {
+        let Some(PendingConnect {
+            tunnel,
+            target_host,
+            target_port,
+            ..
+        }) = self.pending.front()
+        else {
+            return false;
+        };
+        if tunnel.is_stopped() {
+            if let Some(mut pending) = self.pending.pop_front() {
+                pending.send_socks_reply(socks::REPLY_GENERAL_FAILURE);
+            }
+            return true;
+        }
+
+        match self
+            .sess
+            .channel_direct_tcpip(target_host, *target_port, None)
+        {
+            Ok(channel) => {
+                let Some(mut pending) = self.pending.pop_front() else {
+                    return true;
+                };
+                pending.send_socks_reply(socks::REPLY_SUCCEEDED);
+                let PendingConnect { tunnel, stream, .. } = pending;
+                debug!("Opened forwarded channel for {}", tunnel.kind.describe());
+                tunnel.connections.fetch_add(1, Ordering::Relaxed);
+                self.relays.push(ActiveRelay {
+                    tunnel,
+                    relay: Relay::new(channel, stream),
+                });
+                true
+            }
+            Err(e) if would_block(&e) => false,
+            Err(e) => {
+                warn!(
+                    "Failed to open channel to {}:{} via {}: {}",
+                    target_host, target_port, self.host.alias, e
+                );
+                if let Some(mut pending) = self.pending.pop_front() {
+                    pending.send_socks_reply(socks::REPLY_CONNECTION_REFUSED);
+                }
+                true
+            }
+        }
+    }
+
+    /// Requests the server-side listeners that are missing, one at a time since
+    /// libssh2 tracks a single pending forward request per session.
+    fn listen_remote(&mut self) -> bool {
+        let before = self.remote.len();
+        self.remote.retain(|r| !r.tunnel.is_stopped());
+        let mut progressed = self.remote.len() != before;
+
+        let now = Instant::now();
+        for remote in self.remote.iter_mut().filter(|r| r.listener.is_none()) {
+            if remote.retry_at.is_some_and(|at| at > now) {
+                continue;
+            }
+            let TunnelKind::Remote { remote_port, .. } = remote.tunnel.kind else {
+                continue;
+            };
+            match self
+                .sess
+                .channel_forward_listen(remote_port, Some(REMOTE_BIND_ADDRESS), None)
+            {
+                Ok((listener, _)) => {
+                    debug!("Listening on remote port {}", remote_port);
+                    remote.listener = Some(listener);
+                    remote.retry_at = None;
+                    if let Some(ready) = remote.ready.take() {
+                        let _ = ready.send(Ok(()));
+                    }
+                    progressed = true;
+                }
+                Err(e) if would_block(&e) => break,
+                Err(e) => {
+                    if let Some(ready) = remote.ready.take() {
+                        let _ = ready.send(Err(anyhow::anyhow!(
+                            "Server refused to listen on port {}: {}",
+                            remote_port,
+                            e
+                        )));
+                    } else {
+                        warn!(
+                            "Server refused to listen on port {} again: {}",
+                            remote_port, e
+                        );
+                        remote.retry_at = Some(now + RELISTEN_DELAY);
+                    }
+                    progressed = true;
+                }
+            }
+        }
+        progressed
+    }
+
+    /// Accepts connections on the remote listeners and connects each one to its
+    /// local target in the background, so a slow target doesn't stall other tunnels.
+    fn accept_remote(&mut self) -> bool {
+        let mut progressed = false;
+        for remote in &mut self.remote {
+            let Some(listener) = remote.listener.as_mut() else {
+                continue;
+            };
+            let channel = match listener.accept() {
+                Ok(channel) => channel,
+                Err(e) if would_block(&e) => continue,
+                Err(e) => {
+                    warn!(
+                        "Remote listener for {} failed: {}",
+                        remote.tunnel.kind.describe(),
+                        e
+                    );
+                    remote.listener = None;
+                    remote.retry_at = Some(Instant::now() + RELISTEN_DELAY);
+                    progressed = true;
+                    continue;
+                }
+            };
+            progressed = true;
+
+            let TunnelKind::Remote {
+                local_host,
+                local_port,
+                ..
+            } = &remote.tunnel.kind
+            else {
+                continue;
+            };
+            let target = (local_host.clone(), *local_port);
+            let tunnel = remote.tunnel.clone();
+            let tx = self.tx.clone();
+            self.runtime.spawn(async move {
+                let stream = tokio::net::TcpStream::connect((target.0.as_str(), target.1))
+                    .await
+                    .and_then(|stream| stream.into_std());
+                let _ = tx.send(ForwarderCommand::Attach {
+                    tunnel,
+                    channel,
+                    stream,
+                });
+            });
+        }
+        progressed
+    }
+
+    fn pump_relays(&mut self, buf: &mut [u8]) -> bool {
+        let mut progressed = false;
+        let mut i = 0;
+        while i < self.relays.len() {
+            let active = &mut self.relays[i];
+            let finished = if active.tunnel.is_stopped() {
+                true
+            } else {
+                match active.relay.pump(buf) {
+                    Ok(progress) => {
+                        active
+                            .tunnel
+                            .bytes_sent
+                            .fetch_add(progress.sent as u64, Ordering::Relaxed);
+                        active
+                            .tunnel
+                            .bytes_received
+                            .fetch_add(progress.received as u64, Ordering::Relaxed);
+                        progressed |= progress.progressed;
+                        progress.done
+                    }
+                    Err(e) => {
+                        debug!("Forwarded connection closed: {}", e);
+                        true
+                    }
+                }
+            };
+            if finished {
+                let active = self.relays.swap_remove(i);
+                active.tunnel.connections.fetch_sub(1, Ordering::Relaxed);
+                active.relay.close();
+                progressed = true;
+            } else {
+                i += 1;
+            }
+        }
+        progressed
+    }
+
+    /// Replaces a dead session. Connections in flight are lost; local tunnels keep
+    /// their listeners and reverse tunnels are requested again on the new session.
+    /// Returns `false` when there is nothing left to reconnect for.
+    fn reconnect(&mut self) -> bool {
+        for active in self.relays.drain(..) {
+            active.tunnel.connections.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.pending.clear();
+        for remote in &mut self.remote {
+            remote.listener = None;
+            remote.retry_at = None;
+        }
+
+        // The dead session would otherwise pass the pool's keepalive probe.
+        self.runtime
+            .block_on(invalidate_forwarding_session(&self.host));
+        let mut delay = RECONNECT_DELAY;
+        loop {
+            if self.should_exit() {
+                return false;
+            }
+            match self.runtime.block_on(establish_forwarding_session(
+                &self.host,
+                self.password.as_deref(),
+            )) {
+                Ok(sess) => {
+                    info!("Forwarding session to {} re-established", self.host.alias);
+                    sess.set_keepalive(true, KEEPALIVE_INTERVAL);
+                    sess.set_blocking(false);
+                    self.sess = sess;
+                    return true;
+                }
+                Err(e) => {
+                    warn!(
+                        "Reconnecting forwarding session to {} failed: {}",
+                        self.host.alias, e
+                    );
+                    std::thread::sleep(delay);
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                }
+            }
+        }
+    }
+}
+
+/// Starts forwarding `127.0.0.1:bind_port` to `target_host:target_port` through
+/// the host, like `ssh -L`. The tunnel runs until [`Tunnel::stop`] is called.
+#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias))]
+pub async fn start_local_forward(
+    host: &SshHost,
+    password: Option<&str>,
+    bind_port: u16,
+    target_host: &str,
+    target_port: u16,
+) -> anyhow::Result<Arc<Tunnel>> {
+    let kind = TunnelKind::Local {
+        bind_port,
+        target_host: target_host.to_string(),
+        target_port,
+    };
+    start_local_listener(host, password, kind).await
+}
+
+/// Starts a SOCKS5 proxy on `127.0.0.1:bind_port` whose connections are opened
+/// from the host, like `ssh -D`. The tunnel runs until [`Tunnel::stop`] is called.
+#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias))]
+pub async fn start_dynamic_forward(
+    host: &SshHost,
+    password: Option<&str>,
+    bind_port: u16,
+) -> anyhow::Result<Arc<Tunnel>> {
+    start_local_listener(host, password, TunnelKind::Dynamic { bind_port }).await
+}
+
+/// Binds the local port of a `Local` or `Dynamic` tunnel and hands every accepted
+/// connection to the host's forwarder.
+async fn start_local_listener(
+    host: &SshHost,
+    password: Option<&str>,
+    kind: TunnelKind,
+) -> anyhow::Result<Arc<Tunnel>> {
+    let (TunnelKind::Local { bind_port, .. } | TunnelKind::Dynamic { bind_port }) = kind else {
+        anyhow::bail!("{} does not listen locally", kind.describe());
+    };
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", bind_port))
+        .await
+        .with_context(|| format!("Failed to listen on localhost:{}", bind_port))?;
+
+    let tunnel = Arc::new(Tunnel::new(&host.alias, kind));
+    // Registered before the forwarder is started so it never sees a host without tunnels.
+    register_tunnel(tunnel.clone())?;
+    if let Err(e) = get_forwarder(host, password).await {
+        tunnel.stop();
+        return Err(e);
+    }
+    info!(
+        "Started tunnel {} on {}",
+        tunnel.kind.describe(),
+        host.alias
+    );
+
+    let accept_tunnel = tunnel.clone();
+    let host = host.clone();
+    let password = password.map(str::to_string);
+    tokio::spawn(async move {
+        let tunnel = accept_tunnel;
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = tunnel.stop_notify.notified() => break,
+            };
+            let stream = match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Failed to accept forwarded connection: {}", e);
+                    continue;
+                }
+            };
+            if tunnel.is_stopped() {
+                break;
+            }
+            let tunnel = tunnel.clone();
+            let host = host.clone();
+            let password = password.clone();
+            // Each connection gets its own task so a slow SOCKS client can't hold up the others.
+            tokio::spawn(async move {
+                if let Err(e) = forward_connection(&host, password.as_deref(), tunnel, stream).await
+                {
+                    warn!("Dropping forwarded connection: {:#}", e);
+                }
+            });
+        }
+        debug!("Listener on localhost:{} closed", bind_port);
+    });
+
+    Ok(tunnel)
+}
+
+async fn forward_connection(
+    host: &SshHost,
+    password: Option<&str>,
+    tunnel: Arc<Tunnel>,
+    mut stream: tokio::net::TcpStream,
+) -> anyhow::Result<()> {
+    let (target_host, target_port, socks) = match &tunnel.kind {
+        TunnelKind::Local {
+            target_host,
+            target_port,
+            ..
+        } => (target_host.clone(), *target_port, false),
+        TunnelKind::Dynamic { .. } => {
+            let (target_host, target_port) = socks::read_connect_request(&mut stream).await?;
+            debug!("SOCKS5 CONNECT {}:{}", target_host, target_port);
+            (target_host, target_port, true)
+        }
+        TunnelKind::Remote { .. } => anyhow::bail!("Reverse tunnels don't accept locally"),
+    };
+    let command = ForwarderCommand::Connect(PendingConnect {
+        tunnel,
+        stream: stream.into_std()?,
+        target_host,
+        target_port,
+        socks,
+    });
+    send_to_forwarder(host, password, command).await
+}
+
+/// Asks the server to listen on `remote_port` and forwards every connection made to
+/// it back to `local_host:local_port`, like `ssh -R`. The listener is requested
+/// again whenever the forwarding session is re-established.
+#[instrument(skip(password), fields(host = %host.hostname, alias = %host.alias))]
+pub async fn start_remote_forward(
+    host: &SshHost,
+    password: Option<&str>,
+    remote_port: u16,
+    local_host: &str,
+    local_port: u16,
+) -> anyhow::Result<Arc<Tunnel>> {
+    let tunnel = Arc::new(Tunnel::new(
+        &host.alias,
+        TunnelKind::Remote {
+            remote_port,
+            local_host: local_host.to_string(),
+            local_port,
+        },
+    ));
+    register_tunnel(tunnel.clone())?;
+
+    let (ready, ready_rx) = oneshot::channel();
+    let command = ForwarderCommand::Listen {
+        tunnel: tunnel.clone(),
+        ready,
+    };
+    let result = match send_to_forwarder(host, password, command).await {
+        Ok(()) => ready_rx
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("Forwarder for {} stopped", host.alias))),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        tunnel.stop();
+        return Err(e);
+    }
+    info!(
+        "Started tunnel {} on {}",
+        tunnel.kind.describe(),
+        host.alias
+    );
+    Ok(tunnel)
+}
@@ -0,0 +1,414 @@
+use crate::config_observer::{SshHost, expand_tilde, parse_tags};
+use anyhow::Context;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where hosts are imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    /// A Termius JSON export.
+    Termius,
+    /// PuTTY's `~/.putty/sessions` directory, or one session file from it.
+    Putty,
+    /// A directory of Remmina `.remmina` profiles, or one profile.
+    Remmina,
+    /// CSV with a header row: `alias,hostname,user,port,password,identity_file,folder,tags`.
+    Csv,
+}
+
+impl ImportSource {
+    pub const ALL: [ImportSource; 4] = [Self::Termius, Self::Putty, Self::Remmina, Self::Csv];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Termius => "Termius (JSON export)",
+            Self::Putty => "PuTTY sessions",
+            Self::Remmina => "Remmina profiles",
+            Self::Csv => "CSV",
+        }
+    }
+
+    /// Whether the source is read from a directory rather than a single file.
+    pub fn reads_directory(self) -> bool {
+        matches!(self, Self::Putty | Self::Remmina)
+    }
+
+    /// Where the application keeps its data by default, if it has a fixed place.
+    pub fn default_location(self) -> Option<PathBuf> {
+        match self {
+            Self::Putty => Some(expand_tilde("~/.putty/sessions")),
+            Self::Remmina => Some(expand_tilde("~/.local/share/remmina")),
+            Self::Termius | Self::Csv => None,
+        }
+    }
+}
+
+/// A host read from another client, with the password it stored, if any.
+#[derive(Debug, Clone, Default)]
+pub struct ImportedHost {
+    pub host: SshHost,
+    pub password: Option<String>,
+}
+
+/// Reads every SSH host `source` has at `path`.
+pub fn import_hosts(source: ImportSource, path: &Path) -> anyhow::Result<Vec<ImportedHost>> {
+    match source {
+        ImportSource::Termius => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            parse_termius(&content)
+        }
+        ImportSource::Putty => read_each(path, None, |name, content| {
+            Ok(parse_putty_session(&putty_session_name(name), content))
+        }),
+        ImportSource::Remmina => read_each(path, Some("remmina"), |_, content| {
+            Ok(parse_remmina_profile(content))
+        }),
+        ImportSource::Csv => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            parse_csv(&content)
+        }
+    }
+}
+
+/// Runs `parse` on `path`, or on each file in it (optionally only those with
+/// `extension`) when it is a directory.
+fn read_each(
+    path: &Path,
+    extension: Option<&str>,
+    parse: impl Fn(&str, &str) -> anyhow::Result<Option<ImportedHost>>,
+) -> anyhow::Result<Vec<ImportedHost>> {
+    let mut files = if path.is_dir() {
+        fs::read_dir(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|p| p.is_file())
+            .filter(|p| extension.is_none_or(|ext| p.extension().is_some_and(|e| e == ext)))
+            .collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+    files.sort();
+
+    let mut hosts = Vec::new();
+    for file in files {
+        let content = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        hosts.extend(parse(&name, &content)?);
+    }
+    Ok(hosts)
+}
+
+/// Looks up the first of `paths` (dot-separated keys) holding a string or number.
+fn json_field(value: &Value, paths: &[&str]) -> Option<String> {
+    paths.iter().find_map(|path| {
+        let field = path
+            .split('.')
+            .try_fold(value, |current, key| current.get(key))?;
+        match field {
+            Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+    })
+}
+
+/// Parses a Termius export: a list of hosts, or an object with a `hosts` list.
+/// Both the flat layout and the nested `ssh_config.identity` one are accepted.
+fn parse_termius(content: &str) -> anyhow::Result<Vec<ImportedHost>> {
+    let root: Value = serde_json::from_str(content).context("Invalid Termius JSON")?;
+    let entries = match &root {
+        Value::Array(entries) => entries,
+        _ => root
+            .get("hosts")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow::anyhow!("No hosts found in Termius export"))?,
+    };
+
+    let mut hosts = Vec::new();
+    for entry in entries {
+        let Some(hostname) = json_field(entry, &["address", "hostname", "host"]) else {
+            continue;
+        };
+        let tags = match entry.get("tags") {
+            Some(Value::Array(tags)) => tags
+                .iter()
+                .filter_map(|tag| match tag {
+                    Value::String(s) => Some(s.clone()),
+                    other => json_field(other, &["label", "name"]),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            _ => String::new(),
+        };
+        hosts.push(ImportedHost {
+            host: SshHost {
+                alias: json_field(entry, &["label", "alias", "name"])
+                    .unwrap_or_else(|| hostname.clone()),
+                user: json_field(
+                    entry,
+                    &[
+                        "username",
+                        "user",
+                        "identity.username",
+                        "ssh_config.identity.username",
+                    ],
+                ),
+                port: json_field(entry, &["port", "ssh_config.port"]).and_then(|p| p.parse().ok()),
+                folder: json_field(entry, &["group", "group.label", "group.name"]),
+                tags: parse_tags(&tags),
+                hostname,
+                ..Default::default()
+            },
+            password: json_field(
+                entry,
+                &[
+                    "password",
+                    "identity.password",
+                    "ssh_config.identity.password",
+                ],
+            ),
+        });
+    }
+    Ok(hosts)
+}
+
+/// Decodes PuTTY's percent-encoded session file names (`My%20Server`).
+fn putty_session_name(file_name: &str) -> String {
+    let bytes = file_name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = file_name
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The `key=value` lines of a PuTTY session file.
+fn key_values(content: &str) -> impl Iterator<Item = (&str, &str)> {
+    content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+}
+
+/// Parses one PuTTY session. Non-SSH sessions and PuTTY's "Default Settings"
+/// are skipped; `.ppk` keys can't be used by OpenSSH, so `PublicKeyFile` is not
+/// carried over.
+fn parse_putty_session(name: &str, content: &str) -> Option<ImportedHost> {
+    if name == "Default Settings" {
+        return None;
+    }
+    let mut host = SshHost {
+        alias: name.to_string(),
+        ..Default::default()
+    };
+    let mut protocol = None;
+    for (key, value) in key_values(content) {
+        match key {
+            "HostName" => match value.rsplit_once('@') {
+                Some((user, hostname)) => {
+                    host.user = Some(user.to_string()).filter(|u| !u.is_empty());
+                    host.hostname = hostname.to_string();
+                }
+                None => host.hostname = value.to_string(),
+            },
+            "UserName" if !value.is_empty() => host.user = Some(value.to_string()),
+            "PortNumber" => host.port = value.parse().ok(),
+            "Protocol" => protocol = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if host.hostname.is_empty() || protocol.is_some_and(|p| p != "ssh") {
+        return None;
+    }
+    Some(ImportedHost {
+        host,
+        password: None,
+    })
+}
+
+/// Parses the `[remmina]` section of an SSH profile. Remmina encrypts stored
+/// passwords with its own secret, so they aren't imported.
+fn parse_remmina_profile(content: &str) -> Option<ImportedHost> {
+    let mut host = SshHost::default();
+    let mut protocol = String::new();
+    let mut in_section = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_section = line == "[remmina]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_section) else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "name" => host.alias = value.to_string(),
+            "protocol" => protocol = value.to_string(),
+            "server" => match value.rsplit_once(':').filter(|(h, _)| !h.ends_with(']')) {
+                Some((hostname, port)) if port.parse::<u16>().is_ok() => {
+                    host.hostname = hostname.trim_matches(['[', ']']).to_string();
+                    host.port = port.parse().ok();
+                }
+                _ => host.hostname = value.trim_matches(['[', ']']).to_string(),
+            },
+            "username" | "ssh_username" if !value.is_empty() => host.user = Some(value.to_string()),
+            "ssh_privatekey" if !value.is_empty() => host.identity_file = Some(value.to_string()),
+            "group" if !value.is_empty() => host.folder = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if !protocol.eq_ignore_ascii_case("ssh") || host.hostname.is_empty() {
+        return None;
+    }
+    if host.alias.is_empty() {
+        host.alias = host.hostname.clone();
+    }
+    Some(ImportedHost {
+        host,
+        password: None,
+    })
+}
+
+/// Splits one CSV record, honouring double quotes and `""` escapes.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+/// Parses CSV whose first row names the columns. `hostname` (or `host`) is
+/// required; `alias` defaults to the hostname and `tags` are `;`- or
+/// `,`-separated inside their field.
+fn parse_csv(content: &str) -> anyhow::Result<Vec<ImportedHost>> {
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+    let header: Vec<String> = csv_fields(lines.next().unwrap_or_default())
+        .into_iter()
+        .map(|h| h.to_lowercase())
+        .collect();
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let hostname_col = column(&["hostname", "host", "address"])
+        .ok_or_else(|| anyhow::anyhow!("CSV needs a \"hostname\" column"))?;
+    let alias_col = column(&["alias", "name", "label"]);
+    let user_col = column(&["user", "username"]);
+    let port_col = column(&["port"]);
+    let password_col = column(&["password"]);
+    let identity_col = column(&["identity_file", "identityfile", "key"]);
+    let folder_col = column(&["folder", "group"]);
+    let tags_col = column(&["tags"]);
+
+    let mut hosts = Vec::new();
+    for line in lines {
+        let fields = csv_fields(line);
+        let field = |col: Option<usize>| {
+            col.and_then(|c| fields.get(c))
+                .filter(|f| !f.is_empty())
+                .cloned()
+        };
+        let Some(hostname) = field(Some(hostname_col)) else {
+            continue;
+        };
+        hosts.push(ImportedHost {
+            host: SshHost {
+                alias: field(alias_col).unwrap_or_else(|| hostname.clone()),
+                user: field(user_col),
+                port: field(port_col).and_then(|p| p.parse().ok()),
+                identity_file: field(identity_col),
+                folder: field(folder_col),
+                tags: parse_tags(&field(tags_col).unwrap_or_default().replace(';', ",")),
+                hostname,
+                ..Default::default()
+            },
+            password: field(password_col),
+        });
+    }
+    Ok(hosts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_termius_export_reads_flat_and_nested_hosts() {
+        let json = r#"{"hosts": [
+            {"label": "web", "address": "10.0.0.1", "port": 2222, "username": "deploy",
+             "group": {"label": "prod"}, "tags": ["nginx", {"label": "eu"}]},
+            {"address": "db.internal",
+             "ssh_config": {"port": 22, "identity": {"username": "pg", "password": "s3cret"}}}
+        ]}"#;
+        let hosts = parse_termius(json).unwrap();
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts[0].host.alias, "web");
+        assert_eq!(hosts[0].host.port, Some(2222));
+        assert_eq!(hosts[0].host.folder.as_deref(), Some("prod"));
+        assert_eq!(hosts[0].host.tags, vec!["nginx", "eu"]);
+        assert_eq!(hosts[1].host.alias, "db.internal");
+        assert_eq!(hosts[1].host.user.as_deref(), Some("pg"));
+        assert_eq!(hosts[1].password.as_deref(), Some("s3cret"));
+    }
+
+    #[test]
+    fn test_putty_and_remmina_profiles_map_to_hosts() {
+        let session = "HostName=admin@gw.example.com\nPortNumber=2200\nProtocol=ssh\n";
+        let imported = parse_putty_session(&putty_session_name("My%20Gateway"), session).unwrap();
+        assert_eq!(imported.host.alias, "My Gateway");
+        assert_eq!(imported.host.user.as_deref(), Some("admin"));
+        assert_eq!(imported.host.hostname, "gw.example.com");
+        assert_eq!(imported.host.port, Some(2200));
+        assert!(parse_putty_session("rdp", "HostName=x\nProtocol=telnet\n").is_none());
+
+        let profile = "[remmina]\nname=Lab box\nprotocol=SSH\nserver=192.168.1.20:2022\nusername=lab\ngroup=lab\n";
+        let imported = parse_remmina_profile(profile).unwrap();
+        assert_eq!(imported.host.alias, "Lab box");
+        assert_eq!(imported.host.hostname, "192.168.1.20");
+        assert_eq!(imported.host.port, Some(2022));
+        assert_eq!(imported.host.folder.as_deref(), Some("lab"));
+        assert!(parse_remmina_profile("[remmina]\nprotocol=RDP\nserver=x\n").is_none());
+    }
+
+    #[test]
+    fn test_csv_honours_header_order_and_quotes() {
+        let csv = "Host,Alias,Port,Tags,Password\n\
+                   10.0.0.5,\"web, primary\",2222,\"a;b\",\"pa\"\"ss\"\n\
+                   10.0.0.6,,,,\n";
+        let hosts = parse_csv(csv).unwrap();
+        assert_eq!(hosts[0].host.alias, "web, primary");
+        assert_eq!(hosts[0].host.port, Some(2222));
+        assert_eq!(hosts[0].host.tags, vec!["a", "b"]);
+        assert_eq!(hosts[0].password.as_deref(), Some("pa\"ss"));
+        assert_eq!(hosts[1].host.alias, "10.0.0.6");
+        assert!(parse_csv("alias,user\nx,y\n").is_err());
+    }
+}
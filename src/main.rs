@@ -1,5 +1,9 @@
+mod backup;
 mod config_observer;
+mod credentials;
+mod crypto;
 mod engines;
+mod import;
 mod ui;
 
 use crate::ui::window::build_ui;
@@ -23,9 +27,34 @@ async fn main() {
     }
 
     if let Ok(alias) = std::env::var("RUSTMIUS_ASKPASS_ALIAS") {
-        if let Some(pass_str) = crate::config_observer::get_keyring_password(&alias).await {
-            // Wipe the plaintext password from memory once it has been written out.
-            let pass_str = zeroize::Zeroizing::new(pass_str);
+        // ssh passes its prompt as the first argument; key prompts mention the passphrase.
+        let prompt = std::env::args().nth(1).unwrap_or_default();
+        let key_path = std::env::var("RUSTMIUS_ASKPASS_KEY")
+            .ok()
+            .filter(|_| prompt.to_lowercase().contains("passphrase"));
+        // Secrets from an unlocked vault come from the app over its askpass socket.
+        let from_app = match (
+            std::env::var("RUSTMIUS_ASKPASS_SOCKET"),
+            std::env::var("RUSTMIUS_ASKPASS_TOKEN"),
+        ) {
+            (Ok(socket), Ok(token)) => crate::credentials::askpass_request(
+                std::path::Path::new(&socket),
+                &token,
+                key_path.is_some(),
+            ),
+            _ => None,
+        };
+        let secret = match (from_app, key_path) {
+            (Some(secret), _) => Some(secret),
+            (None, Some(key_path)) => crate::config_observer::get_keyring_passphrase(&key_path)
+                .await
+                .map(zeroize::Zeroizing::new),
+            (None, None) => crate::config_observer::get_keyring_password(&alias)
+                .await
+                .map(zeroize::Zeroizing::new),
+        };
+        // The plaintext is wiped from memory once it has been written out.
+        if let Some(pass_str) = secret {
             use std::io::Write;
             let mut stdout = std::io::stdout().lock();
             let _ = stdout.write_all(pass_str.as_bytes());
@@ -41,5 +70,6 @@ async fn main() {
         .build();
 
     app.connect_activate(build_ui);
+    app.connect_shutdown(|_| crate::credentials::stop_askpass_server());
     app.run_with_args::<&str>(&[]);
 }
@@ -1,7 +1,52 @@
 #![allow(deprecated)]
-use crate::config_observer::{SshHost, load_ssh_keys};
+use crate::config_observer::{SshHost, SshKeyPair, expand_tilde, load_hosts, load_ssh_keys};
+use crate::ui::diagnostics_dialog::show_diagnostics_dialog;
+use gtk4::glib;
 use gtk4::prelude::*;
 
+/// Forwarding needs the system `ssh` client, which the built-in one can't replace.
+const FORWARDING_NOTE: &str = "Terminals for this host open with the system ssh client, even when the built-in terminal is enabled";
+
+/// Dropdown position of the host's `IdentityFile` among `keys` (0 is "None").
+fn initial_key_index(host: Option<&SshHost>, keys: &[SshKeyPair]) -> u32 {
+    let Some(id_file) = host.and_then(|h| h.identity_file.as_deref()) else {
+        return 0;
+    };
+    let id_file = expand_tilde(id_file);
+    keys.iter()
+        .position(|k| k.priv_path == id_file)
+        .map_or(0, |i| i as u32 + 1)
+}
+
+/// `IdentityFile` and `CertificateFile` for the key picked in the dropdown. An
+/// untouched selection keeps the host's values as written, even ones the
+/// dropdown can't show.
+fn selected_key_files(
+    base: &SshHost,
+    keys: &[SshKeyPair],
+    initial: u32,
+    selected: u32,
+) -> (Option<String>, Option<String>) {
+    if selected == initial {
+        return (base.identity_file.clone(), base.certificate_file.clone());
+    }
+    match selected.checked_sub(1).and_then(|i| keys.get(i as usize)) {
+        Some(key) => (
+            Some(key.priv_path.to_string_lossy().to_string()),
+            key.certificate
+                .as_ref()
+                .map(|cert| cert.to_string_lossy().to_string()),
+        ),
+        None => (None, base.certificate_file.clone()),
+    }
+}
+
+/// Whether `alias` is taken; `existing_aliases` are lowercased, as passed to
+/// [`show_server_dialog`].
+pub fn is_duplicate_alias(existing_aliases: &[String], alias: &str) -> bool {
+    existing_aliases.contains(&alias.trim().to_lowercase())
+}
+
 pub fn show_server_dialog<F>(
     parent: &gtk4::Window,
     initial_host: Option<&SshHost>,
@@ -40,6 +85,40 @@ pub fn show_server_dialog<F>(
     let user_entry = gtk4::Entry::builder()
         .placeholder_text("User (default: root)")
         .build();
+    let jump_entry = gtk4::Entry::builder()
+        .placeholder_text("Jump hosts, comma-separated (e.g. bastion, user@gw:2222)")
+        .build();
+    let folder_entry = gtk4::Entry::builder()
+        .placeholder_text("Folder (e.g. prod, staging, lab)")
+        .build();
+    let folders = gtk4::ListStore::new(&[glib::Type::STRING]);
+    let mut known_folders: Vec<String> = load_hosts()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|h| h.folder)
+        .collect();
+    known_folders.sort();
+    known_folders.dedup();
+    for folder in &known_folders {
+        folders.set(&folders.append(), &[(0, folder)]);
+    }
+    let folder_completion = gtk4::EntryCompletion::builder()
+        .model(&folders)
+        .text_column(0)
+        .minimum_key_length(0)
+        .build();
+    folder_entry.set_completion(Some(&folder_completion));
+    let tags_entry = gtk4::Entry::builder()
+        .placeholder_text("Tags, comma-separated (e.g. web, db)")
+        .build();
+    let agent_check = gtk4::CheckButton::builder()
+        .label("Forward SSH agent (ForwardAgent)")
+        .tooltip_text(FORWARDING_NOTE)
+        .build();
+    let x11_check = gtk4::CheckButton::builder()
+        .label("Forward X11 (ForwardX11)")
+        .tooltip_text(FORWARDING_NOTE)
+        .build();
     let pass_entry = gtk4::PasswordEntry::builder()
         .placeholder_text("Password (leave empty to keep current or no password)")
         .show_peek_icon(true)
@@ -61,6 +140,11 @@ pub fn show_server_dialog<F>(
         if let Some(port) = host.port {
             port_entry.set_text(&port.to_string());
         }
+        jump_entry.set_text(&host.proxy_jump.join(", "));
+        folder_entry.set_text(host.folder.as_deref().unwrap_or_default());
+        tags_entry.set_text(&host.tags.join(", "));
+        agent_check.set_active(host.forward_agent);
+        x11_check.set_active(host.forward_x11);
     }
 
     let keys = load_ssh_keys().unwrap_or_else(|e| {
@@ -74,17 +158,8 @@ pub fn show_server_dialog<F>(
     }
     let key_dropdown = gtk4::DropDown::new(Some(key_model), gtk4::Expression::NONE);
 
-    if let Some(host) = initial_host
-        && let Some(ref id_file) = host.identity_file
-    {
-        let id_file_expanded = crate::config_observer::expand_tilde(id_file);
-        for (i, k) in keys.iter().enumerate() {
-            if k.priv_path == id_file_expanded {
-                key_dropdown.set_selected((i + 1) as u32);
-                break;
-            }
-        }
-    }
+    let initial_key = initial_key_index(initial_host, &keys);
+    key_dropdown.set_selected(initial_key);
 
     content.append(
         &gtk4::Label::builder()
@@ -129,6 +204,35 @@ pub fn show_server_dialog<F>(
             .build(),
     );
     content.append(&key_dropdown);
+    content.append(
+        &gtk4::Label::builder()
+            .label("Jump Hosts (ProxyJump)")
+            .halign(gtk4::Align::Start)
+            .build(),
+    );
+    content.append(&jump_entry);
+    content.append(
+        &gtk4::Label::builder()
+            .label("Folder")
+            .halign(gtk4::Align::Start)
+            .build(),
+    );
+    content.append(&folder_entry);
+    content.append(
+        &gtk4::Label::builder()
+            .label("Tags")
+            .halign(gtk4::Align::Start)
+            .build(),
+    );
+    content.append(&tags_entry);
+    content.append(&agent_check);
+    content.append(&x11_check);
+
+    let test_button = gtk4::Button::builder()
+        .label("Test Connection")
+        .halign(gtk4::Align::Start)
+        .build();
+    content.append(&test_button);
 
     let ok_button = dialog.add_button(
         if initial_host.is_some() {
@@ -148,30 +252,63 @@ pub fn show_server_dialog<F>(
     let existing_aliases_clone = existing_aliases.clone();
     alias_entry.connect_changed(move |e| {
         let text = e.text().to_string().trim().to_lowercase();
-        let is_duplicate =
-            existing_aliases_clone.contains(&text) && Some(text.clone()) != initial_alias;
+        let is_duplicate = is_duplicate_alias(&existing_aliases_clone, &text)
+            && Some(text.clone()) != initial_alias;
         error_label_clone.set_visible(is_duplicate);
         ok_button_clone.set_sensitive(!is_duplicate && !text.is_empty());
     });
 
+    // Directives the dialog doesn't show (algorithms, timeouts…) are kept as is.
+    let base_host = initial_host.cloned().unwrap_or_default();
+    let read_host = Rc::new(move || {
+        let (identity_file, certificate_file) =
+            selected_key_files(&base_host, &keys, initial_key, key_dropdown.selected());
+        SshHost {
+            alias: alias_entry_clone.text().to_string().trim().to_string(),
+            hostname: host_entry.text().to_string().trim().to_string(),
+            user: Some(user_entry.text().to_string().trim().to_string()).filter(|s| !s.is_empty()),
+            port: port_entry.text().to_string().trim().parse::<u16>().ok(),
+            identity_file,
+            certificate_file,
+            proxy_jump: crate::config_observer::parse_proxy_jump(jump_entry.text().trim()),
+            forward_agent: agent_check.is_active(),
+            forward_x11: x11_check.is_active(),
+            folder: Some(folder_entry.text().trim().to_string()).filter(|s| !s.is_empty()),
+            tags: crate::config_observer::parse_tags(&tags_entry.text()),
+            ..base_host.clone()
+        }
+    });
+
+    let test_host = read_host.clone();
+    let test_pass = pass_entry.clone();
+    let test_dialog = dialog.clone();
+    let stored_alias = initial_host.map(|h| h.alias.clone());
+    test_button.connect_clicked(move |_| {
+        let mut host = test_host();
+        if host.hostname.is_empty() {
+            return;
+        }
+        if host.alias.is_empty() {
+            host.alias = host.hostname.clone();
+        }
+        let typed = test_pass.text().to_string();
+        let stored_alias = stored_alias.clone();
+        let parent = test_dialog.clone();
+        glib::MainContext::default().spawn_local(async move {
+            // An empty field keeps the current password, so test with that one.
+            let password = match stored_alias {
+                Some(alias) if typed.is_empty() => {
+                    crate::config_observer::get_keyring_password(&alias).await
+                }
+                _ => Some(typed).filter(|p| !p.is_empty()),
+            };
+            show_diagnostics_dialog(parent.upcast_ref(), host, password);
+        });
+    });
+
     dialog.connect_response(move |d, res| {
         if res == gtk4::ResponseType::Ok {
-            let selected_key_idx = key_dropdown.selected();
-            let identity_file = if selected_key_idx > 0 {
-                let key = &keys[(selected_key_idx - 1) as usize];
-                Some(key.priv_path.to_string_lossy().to_string())
-            } else {
-                None
-            };
-            let host = SshHost {
-                alias: alias_entry_clone.text().to_string().trim().to_string(),
-                hostname: host_entry.text().to_string().trim().to_string(),
-                user: Some(user_entry.text().to_string().trim().to_string())
-                    .filter(|s| !s.is_empty()),
-                port: port_entry.text().to_string().trim().parse::<u16>().ok(),
-                identity_file,
-                identity_agent: None,
-            };
+            let host = read_host();
             let password = pass_entry.text().to_string();
             if !host.alias.is_empty() && !host.hostname.is_empty() {
                 on_save(host, password);
@@ -184,3 +321,33 @@ pub fn show_server_dialog<F>(
 }
 
 use std::rc::Rc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_observer::{SshConfigDocument, parse_ssh_config};
+
+    #[test]
+    fn test_unchanged_dialog_host_leaves_config_untouched() {
+        let config = "Host web\n    HostName 10.0.0.1\n    IdentityFile ~/.ssh/deploy\n    CertificateFile ~/.ssh/deploy-cert.pub\n\nHost listed\n    HostName 10.0.0.2\n    IdentityFile ~/.ssh/id_ed25519\n\nHost db\n    HostName 10.0.0.3\n\nHost *\n    IdentityFile ~/.ssh/id_ed25519\n";
+        let keys = vec![SshKeyPair {
+            name: "id_ed25519".to_string(),
+            pub_path: expand_tilde("~/.ssh/id_ed25519.pub"),
+            priv_path: expand_tilde("~/.ssh/id_ed25519"),
+            certificate: Some(expand_tilde("~/.ssh/id_ed25519-cert.pub")),
+        }];
+        for original in parse_ssh_config(config) {
+            let initial = initial_key_index(Some(&original), &keys);
+            let (identity_file, certificate_file) =
+                selected_key_files(&original, &keys, initial, initial);
+            let updated = SshHost {
+                identity_file,
+                certificate_file,
+                ..original.clone()
+            };
+            let mut doc = SshConfigDocument::parse(config);
+            doc.update_host(&original, &updated);
+            assert_eq!(doc.to_string(), config, "{}", original.alias);
+        }
+    }
+}
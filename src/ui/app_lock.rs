@@ -0,0 +1,316 @@
+#![allow(deprecated)]
+use crate::config_observer::{load_app_config, save_app_config};
+use crate::credentials::{
+    hash_master_password, is_app_locked, is_vault_unlocked, set_app_locked, unlock_vault,
+    vault_exists, verify_master_password,
+};
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const IDLE_CHECK_SECS: u32 = 15;
+
+/// Covers the window with a lock screen at startup and after the configured
+/// idle time, while the app lock is enabled.
+#[derive(Clone)]
+pub struct AppLock {
+    inner: Rc<AppLockInner>,
+}
+
+struct AppLockInner {
+    window: gtk4::ApplicationWindow,
+    stack: gtk4::Stack,
+    password_entry: gtk4::PasswordEntry,
+    error_label: gtk4::Label,
+    unlock_button: gtk4::Button,
+    /// Controls outside `content` (the header buttons) disabled while locked.
+    guarded: Vec<gtk4::Widget>,
+    last_activity: Cell<Instant>,
+    /// The app's other windows (logs, dialogs…), hidden while locked.
+    hidden: RefCell<Vec<glib::WeakRef<gtk4::Window>>>,
+}
+
+impl AppLock {
+    /// Makes `content` the window's child, behind the lock screen.
+    pub fn new(
+        window: &gtk4::ApplicationWindow,
+        content: &impl IsA<gtk4::Widget>,
+        guarded: Vec<gtk4::Widget>,
+    ) -> Self {
+        let stack = gtk4::Stack::new();
+        stack.set_transition_type(gtk4::StackTransitionType::Crossfade);
+        stack.add_named(content, Some("app"));
+
+        let page = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        page.set_halign(gtk4::Align::Center);
+        page.set_valign(gtk4::Align::Center);
+        page.set_width_request(300);
+        let icon = gtk4::Image::from_icon_name("system-lock-screen-symbolic");
+        icon.set_pixel_size(64);
+        icon.add_css_class("dim-label");
+        page.append(&icon);
+        page.append(
+            &gtk4::Label::builder()
+                .label("Rustmius is locked")
+                .css_classes(vec!["title-1".to_string()])
+                .build(),
+        );
+        let password_entry = gtk4::PasswordEntry::builder()
+            .placeholder_text("Master password")
+            .show_peek_icon(true)
+            .build();
+        page.append(&password_entry);
+        let error_label = gtk4::Label::builder()
+            .css_classes(vec!["error".to_string()])
+            .visible(false)
+            .build();
+        page.append(&error_label);
+        let unlock_button = gtk4::Button::with_label("Unlock");
+        unlock_button.add_css_class("suggested-action");
+        page.append(&unlock_button);
+        stack.add_named(&page, Some("locked"));
+        stack.set_visible_child_name("app");
+        window.set_child(Some(&stack));
+
+        let this = Self {
+            inner: Rc::new(AppLockInner {
+                window: window.clone(),
+                stack,
+                password_entry: password_entry.clone(),
+                error_label,
+                unlock_button: unlock_button.clone(),
+                guarded,
+                last_activity: Cell::new(Instant::now()),
+                hidden: RefCell::default(),
+            }),
+        };
+
+        let t = this.clone();
+        password_entry.connect_activate(move |_| t.try_unlock());
+        let t = this.clone();
+        unlock_button.connect_clicked(move |_| t.try_unlock());
+
+        let activity = gtk4::EventControllerLegacy::new();
+        activity.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        let weak = Rc::downgrade(&this.inner);
+        activity.connect_event(move |_, event| {
+            use gtk4::gdk::EventType;
+            if matches!(
+                event.event_type(),
+                EventType::KeyPress
+                    | EventType::ButtonPress
+                    | EventType::MotionNotify
+                    | EventType::Scroll
+                    | EventType::TouchBegin
+            ) && let Some(inner) = weak.upgrade()
+            {
+                inner.last_activity.set(Instant::now());
+            }
+            glib::Propagation::Proceed
+        });
+        window.add_controller(activity);
+
+        let weak = Rc::downgrade(&this.inner);
+        glib::timeout_add_seconds_local(IDLE_CHECK_SECS, move || {
+            let Some(inner) = weak.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            let lock = load_app_config().map(|c| c.app_lock).unwrap_or_default();
+            let idle_limit = Duration::from_secs(u64::from(lock.idle_minutes) * 60);
+            if lock.is_enabled()
+                && lock.idle_minutes > 0
+                && !is_app_locked()
+                && inner.last_activity.get().elapsed() >= idle_limit
+            {
+                tracing::info!("Locking after {} idle minute(s)", lock.idle_minutes);
+                Self { inner }.lock();
+            }
+            glib::ControlFlow::Continue
+        });
+
+        this.lock();
+        this
+    }
+
+    /// Shows the lock screen, if the lock is enabled. Returns whether it did.
+    pub fn lock(&self) -> bool {
+        let enabled = load_app_config()
+            .map(|c| c.app_lock.is_enabled())
+            .unwrap_or(false);
+        if !enabled {
+            return false;
+        }
+        set_app_locked(true);
+        let inner = &self.inner;
+        for widget in &inner.guarded {
+            widget.set_sensitive(false);
+        }
+        inner.stack.set_visible_child_name("locked");
+        let main_window: &gtk4::Window = inner.window.upcast_ref();
+        let mut hidden = inner.hidden.borrow_mut();
+        for window in gtk4::Window::list_toplevels()
+            .into_iter()
+            .filter_map(|w| w.downcast::<gtk4::Window>().ok())
+            .filter(|w| w != main_window && w.is_visible())
+        {
+            window.set_visible(false);
+            hidden.push(window.downgrade());
+        }
+        inner.password_entry.set_text("");
+        inner.error_label.set_visible(false);
+        inner.password_entry.grab_focus();
+        true
+    }
+
+    fn try_unlock(&self) {
+        let Some(hash) = load_app_config()
+            .ok()
+            .and_then(|c| c.app_lock.password_hash)
+        else {
+            self.unlocked();
+            return;
+        };
+        let password = zeroize::Zeroizing::new(self.inner.password_entry.text().to_string());
+        if password.is_empty() {
+            return;
+        }
+        self.inner.unlock_button.set_sensitive(false);
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            // The same password also opens the vault, if it was created with it.
+            let verified = gio::spawn_blocking(move || {
+                let verified = verify_master_password(&password, &hash);
+                if verified && vault_exists() && !is_vault_unlocked() {
+                    let _ = unlock_vault(&password);
+                }
+                verified
+            })
+            .await
+            .unwrap_or(false);
+            let inner = &this.inner;
+            inner.unlock_button.set_sensitive(true);
+            if verified {
+                this.unlocked();
+            } else {
+                inner.error_label.set_text("Wrong master password");
+                inner.error_label.set_visible(true);
+                inner.password_entry.set_text("");
+                inner.password_entry.grab_focus();
+            }
+        });
+    }
+
+    fn unlocked(&self) {
+        let inner = &self.inner;
+        inner.password_entry.set_text("");
+        inner.last_activity.set(Instant::now());
+        inner.stack.set_visible_child_name("app");
+        for widget in &inner.guarded {
+            widget.set_sensitive(true);
+        }
+        set_app_locked(false);
+        for window in inner.hidden.take().iter().filter_map(|w| w.upgrade()) {
+            window.set_visible(true);
+        }
+        crate::ui::vault_dialog::unlock_vault_if_needed(inner.window.upcast_ref());
+    }
+}
+
+/// Asks for a new master password and enables the app lock with it.
+/// `on_done` gets whether the lock was enabled.
+pub fn show_set_password_dialog(parent: &gtk4::Window, on_done: impl Fn(bool) + 'static) {
+    let dialog = gtk4::Dialog::builder()
+        .transient_for(parent)
+        .modal(true)
+        .title("Set Master Password")
+        .default_width(380)
+        .build();
+
+    let content = dialog.content_area();
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    content.set_spacing(12);
+
+    content.append(
+        &gtk4::Label::builder()
+            .label("Rustmius will ask for this password at startup and after being idle.")
+            .halign(gtk4::Align::Start)
+            .wrap(true)
+            .build(),
+    );
+    let password = gtk4::PasswordEntry::builder()
+        .show_peek_icon(true)
+        .activates_default(true)
+        .placeholder_text("Master password")
+        .build();
+    let confirm = gtk4::PasswordEntry::builder()
+        .show_peek_icon(true)
+        .activates_default(true)
+        .placeholder_text("Confirm master password")
+        .build();
+    let error_label = gtk4::Label::builder()
+        .halign(gtk4::Align::Start)
+        .css_classes(vec!["error".to_string()])
+        .visible(false)
+        .build();
+    content.append(&password);
+    content.append(&confirm);
+    content.append(&error_label);
+
+    let ok_button = dialog.add_button("Enable Lock", gtk4::ResponseType::Ok);
+    dialog.add_button("Cancel", gtk4::ResponseType::Cancel);
+    dialog.set_default_widget(Some(&ok_button));
+
+    // `close()` emits another response, so report only the first outcome.
+    let reported = Cell::new(false);
+    let on_done = Rc::new(move |enabled: bool| {
+        if !reported.replace(true) {
+            on_done(enabled);
+        }
+    });
+    dialog.connect_response(move |d, res| {
+        if res != gtk4::ResponseType::Ok {
+            on_done(false);
+            d.close();
+            return;
+        }
+        let text = zeroize::Zeroizing::new(password.text().to_string());
+        if text.is_empty() || confirm.text().as_str() != text.as_str() {
+            error_label.set_text(if text.is_empty() {
+                "Enter a master password."
+            } else {
+                "The passwords don't match."
+            });
+            error_label.set_visible(true);
+            return;
+        }
+        d.set_response_sensitive(gtk4::ResponseType::Ok, false);
+        let (d, on_done, error_label) = (d.clone(), on_done.clone(), error_label.clone());
+        glib::MainContext::default().spawn_local(async move {
+            let result = gio::spawn_blocking(move || {
+                let hash = hash_master_password(&text)?;
+                let mut config = load_app_config().unwrap_or_default();
+                config.app_lock.password_hash = Some(hash);
+                save_app_config(&config)
+            })
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("Hashing task failed")));
+            match result {
+                Ok(()) => {
+                    on_done(true);
+                    d.close();
+                }
+                Err(e) => {
+                    d.set_response_sensitive(gtk4::ResponseType::Ok, true);
+                    error_label.set_text(&format!("{:#}", e));
+                    error_label.set_visible(true);
+                }
+            }
+        });
+    });
+    dialog.present();
+}
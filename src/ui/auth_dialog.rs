@@ -0,0 +1,142 @@
+#![allow(deprecated)]
+use crate::engines::auth_prompt::{AuthAnswer, AuthPrompt, ChallengePrompt, set_auth_prompter};
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use std::cell::RefCell;
+use tokio::sync::oneshot;
+
+/// Routes authentication questions from the engine threads to a dialog on the GTK main loop.
+pub fn install_auth_prompter() {
+    set_auth_prompter(|prompt, reply| {
+        glib::MainContext::default().invoke(move || show_auth_dialog(prompt, reply));
+    });
+}
+
+fn active_window() -> Option<gtk4::Window> {
+    gio::Application::default()
+        .and_downcast::<gtk4::Application>()
+        .and_then(|app| app.active_window())
+}
+
+fn show_auth_dialog(prompt: AuthPrompt, reply: oneshot::Sender<Option<AuthAnswer>>) {
+    let (alias, intro, detail, prompts, can_remember) = match prompt {
+        AuthPrompt::KeyboardInteractive {
+            alias,
+            username,
+            instructions,
+            prompts,
+        } => (
+            alias,
+            format!("The server asks {} for more information.", username),
+            instructions.trim().to_string(),
+            prompts,
+            false,
+        ),
+        AuthPrompt::Passphrase {
+            alias,
+            key_path,
+            retry,
+        } => (
+            alias,
+            format!("The key {} is protected by a passphrase.", key_path),
+            if retry {
+                "Wrong passphrase, please try again.".to_string()
+            } else {
+                String::new()
+            },
+            vec![ChallengePrompt {
+                text: "Passphrase".to_string(),
+                echo: false,
+            }],
+            true,
+        ),
+    };
+
+    let dialog = gtk4::Dialog::builder()
+        .modal(true)
+        .title(format!("Authentication for {}", alias))
+        .default_width(350)
+        .build();
+    if let Some(parent) = active_window() {
+        dialog.set_transient_for(Some(&parent));
+    }
+
+    let content = dialog.content_area();
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    content.set_spacing(12);
+
+    content.append(
+        &gtk4::Label::builder()
+            .label(intro)
+            .halign(gtk4::Align::Start)
+            .wrap(true)
+            .build(),
+    );
+    if !detail.is_empty() {
+        content.append(
+            &gtk4::Label::builder()
+                .label(detail)
+                .halign(gtk4::Align::Start)
+                .wrap(true)
+                .css_classes(vec!["dim-label".to_string()])
+                .build(),
+        );
+    }
+
+    // Each prompt gets an entry; hidden answers (passwords, codes) use a PasswordEntry.
+    let mut entries: Vec<gtk4::Editable> = Vec::new();
+    for p in &prompts {
+        content.append(
+            &gtk4::Label::builder()
+                .label(p.text.trim())
+                .halign(gtk4::Align::Start)
+                .wrap(true)
+                .build(),
+        );
+        let entry: gtk4::Editable = if p.echo {
+            gtk4::Entry::builder()
+                .activates_default(true)
+                .build()
+                .upcast()
+        } else {
+            gtk4::PasswordEntry::builder()
+                .activates_default(true)
+                .show_peek_icon(true)
+                .build()
+                .upcast()
+        };
+        content.append(&entry);
+        entries.push(entry);
+    }
+
+    let remember_check = gtk4::CheckButton::builder()
+        .label("Remember in keyring")
+        .visible(can_remember)
+        .build();
+    content.append(&remember_check);
+
+    let ok_button = dialog.add_button("Continue", gtk4::ResponseType::Ok);
+    ok_button.add_css_class("suggested-action");
+    dialog.add_button("Cancel", gtk4::ResponseType::Cancel);
+    dialog.set_default_response(gtk4::ResponseType::Ok);
+
+    let first_entry = entries.first().cloned();
+    let reply = RefCell::new(Some(reply));
+    dialog.connect_response(move |d, response| {
+        let answer = (response == gtk4::ResponseType::Ok).then(|| AuthAnswer {
+            answers: entries.iter().map(|e| e.text().to_string()).collect(),
+            remember: remember_check.is_active(),
+        });
+        if let Some(tx) = reply.borrow_mut().take() {
+            let _ = tx.send(answer);
+        }
+        d.close();
+    });
+    dialog.present();
+    if let Some(entry) = first_entry {
+        entry.grab_focus();
+    }
+}
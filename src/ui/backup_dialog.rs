@@ -0,0 +1,301 @@
+#![allow(deprecated)]
+use crate::backup::{
+    BackupBundle, ConflictPolicy, apply_restore, collect_backup, plan_restore, read_backup,
+    write_backup,
+};
+use crate::config_observer::{load_hosts, store_keyring_password};
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const CONFLICT_POLICIES: [ConflictPolicy; 3] = [
+    ConflictPolicy::KeepExisting,
+    ConflictPolicy::Replace,
+    ConflictPolicy::KeepBoth,
+];
+
+fn dialog_shell(parent: &gtk4::Window, title: &str) -> (gtk4::Dialog, gtk4::Box) {
+    let dialog = gtk4::Dialog::builder()
+        .transient_for(parent)
+        .modal(true)
+        .title(title)
+        .default_width(420)
+        .build();
+    let content = dialog.content_area();
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    content.set_spacing(12);
+    (dialog, content)
+}
+
+fn field_label(text: &str) -> gtk4::Label {
+    gtk4::Label::builder()
+        .label(text)
+        .halign(gtk4::Align::Start)
+        .build()
+}
+
+fn status_label() -> gtk4::Label {
+    gtk4::Label::builder()
+        .halign(gtk4::Align::Start)
+        .wrap(true)
+        .css_classes(vec!["dim-label".to_string()])
+        .build()
+}
+
+/// Asks for a passphrase, then writes the hosts, settings and (optionally)
+/// passwords to an encrypted file.
+pub fn show_export_dialog(parent: &gtk4::Window) {
+    let (dialog, content) = dialog_shell(parent, "Export Backup");
+
+    let passphrase = gtk4::PasswordEntry::builder().show_peek_icon(true).build();
+    let confirm = gtk4::PasswordEntry::builder().show_peek_icon(true).build();
+    let passwords_check = gtk4::CheckButton::builder()
+        .label("Include saved passwords")
+        .build();
+    let status = status_label();
+    status.set_text("The backup can only be restored with this passphrase.");
+
+    content.append(&field_label("Passphrase"));
+    content.append(&passphrase);
+    content.append(&field_label("Confirm passphrase"));
+    content.append(&confirm);
+    content.append(&passwords_check);
+    content.append(&status);
+
+    let export_button = dialog.add_button("Export…", gtk4::ResponseType::Ok);
+    export_button.set_sensitive(false);
+    dialog.add_button("Close", gtk4::ResponseType::Cancel);
+
+    let check_match = {
+        let passphrase = passphrase.clone();
+        let confirm = confirm.clone();
+        let export_button = export_button.clone();
+        move || {
+            let text = passphrase.text();
+            export_button.set_sensitive(!text.is_empty() && text == confirm.text());
+        }
+    };
+    let check_match = Rc::new(check_match);
+    let cm = check_match.clone();
+    passphrase.connect_changed(move |_| cm());
+    confirm.connect_changed(move |_| check_match());
+
+    dialog.connect_response(move |d, res| {
+        if res != gtk4::ResponseType::Ok {
+            d.close();
+            return;
+        }
+        let file_dialog = gtk4::FileDialog::builder()
+            .title("Save Backup")
+            .initial_name(format!(
+                "rustmius-{}.rmbackup",
+                chrono::Local::now().format("%Y%m%d")
+            ))
+            .build();
+        let secret = zeroize::Zeroizing::new(passphrase.text().to_string());
+        let include_passwords = passwords_check.is_active();
+        let status = status.clone();
+        let dialog = d.clone();
+        file_dialog.save(Some(d), gio::Cancellable::NONE, move |res| {
+            let Some(path) = res.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            status.set_text("Exporting…");
+            glib::MainContext::default().spawn_local(async move {
+                let result = match collect_backup(include_passwords).await {
+                    Ok(bundle) => {
+                        let count = (bundle.hosts.len(), bundle.passwords.len());
+                        let path = path.clone();
+                        gio::spawn_blocking(move || {
+                            write_backup(&path, &bundle, &secret).map(|_| count)
+                        })
+                        .await
+                        .unwrap_or_else(|_| Err(anyhow::anyhow!("Export task failed")))
+                    }
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok((hosts, passwords)) => {
+                        status.set_text(&format!(
+                            "Exported {} host(s) and {} password(s) to {}.",
+                            hosts,
+                            passwords,
+                            path.display()
+                        ));
+                        dialog.set_response_sensitive(gtk4::ResponseType::Ok, false);
+                    }
+                    Err(e) => status.set_text(&format!("Export failed: {:#}", e)),
+                }
+            });
+        });
+    });
+
+    dialog.present();
+}
+
+/// Decrypts a backup, shows what it holds and merges it into the current
+/// hosts and settings according to the chosen conflict policy.
+pub fn show_restore_dialog(parent: &gtk4::Window) {
+    let (dialog, content) = dialog_shell(parent, "Restore Backup");
+
+    let file_button = gtk4::Button::with_label("Choose backup file…");
+    let passphrase = gtk4::PasswordEntry::builder().show_peek_icon(true).build();
+    let open_button = gtk4::Button::with_label("Decrypt");
+    open_button.set_halign(gtk4::Align::Start);
+    let summary = status_label();
+    summary.set_text("Choose a backup file and enter its passphrase.");
+
+    let conflict_dropdown = gtk4::DropDown::from_strings(&[
+        "Keep existing hosts",
+        "Replace with the backup",
+        "Keep both (rename restored)",
+    ]);
+    let settings_check = gtk4::CheckButton::builder()
+        .label("Restore settings")
+        .build();
+    let passwords_check = gtk4::CheckButton::builder()
+        .label("Restore saved passwords")
+        .active(true)
+        .build();
+    let options = gtk4::Box::new(gtk4::Orientation::Vertical, 8);
+    options.append(&field_label("When an alias already exists"));
+    options.append(&conflict_dropdown);
+    options.append(&settings_check);
+    options.append(&passwords_check);
+    options.set_sensitive(false);
+
+    content.append(&file_button);
+    content.append(&field_label("Passphrase"));
+    content.append(&passphrase);
+    content.append(&open_button);
+    content.append(&summary);
+    content.append(&options);
+
+    let restore_button = dialog.add_button("Restore", gtk4::ResponseType::Ok);
+    restore_button.set_sensitive(false);
+    dialog.add_button("Close", gtk4::ResponseType::Cancel);
+
+    let path: Rc<RefCell<Option<PathBuf>>> = Rc::new(RefCell::new(None));
+    let bundle: Rc<RefCell<Option<BackupBundle>>> = Rc::new(RefCell::new(None));
+
+    let p = path.clone();
+    let d = dialog.clone();
+    file_button.connect_clicked(move |btn| {
+        let file_dialog = gtk4::FileDialog::builder().title("Open Backup").build();
+        let p = p.clone();
+        let btn = btn.clone();
+        file_dialog.open(Some(&d), gio::Cancellable::NONE, move |res| {
+            if let Some(chosen) = res.ok().and_then(|file| file.path()) {
+                btn.set_label(&chosen.file_name().unwrap_or_default().to_string_lossy());
+                p.replace(Some(chosen));
+            }
+        });
+    });
+
+    let p = path.clone();
+    let b = bundle.clone();
+    let pass = passphrase.clone();
+    let sum = summary.clone();
+    let opts = options.clone();
+    let restore_btn = restore_button.clone();
+    open_button.connect_clicked(move |btn| {
+        let Some(chosen) = p.borrow().clone() else {
+            sum.set_text("Choose a backup file first.");
+            return;
+        };
+        let secret = zeroize::Zeroizing::new(pass.text().to_string());
+        sum.set_text("Decrypting…");
+        btn.set_sensitive(false);
+        let (btn, b, sum, opts, restore_btn) = (
+            btn.clone(),
+            b.clone(),
+            sum.clone(),
+            opts.clone(),
+            restore_btn.clone(),
+        );
+        glib::MainContext::default().spawn_local(async move {
+            let result = gio::spawn_blocking(move || read_backup(&chosen, &secret))
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Decrypt task failed")));
+            btn.set_sensitive(true);
+            match result {
+                Ok(restored) => {
+                    let existing = load_hosts().unwrap_or_default();
+                    let conflicts = restored
+                        .hosts
+                        .iter()
+                        .filter(|h| {
+                            existing
+                                .iter()
+                                .any(|e| e.alias.eq_ignore_ascii_case(&h.alias))
+                        })
+                        .count();
+                    sum.set_text(&format!(
+                        "Backup from {}: {} host(s), {} already here, {} password(s).",
+                        chrono::DateTime::from_timestamp(restored.created_at, 0)
+                            .map(|t| t
+                                .with_timezone(&chrono::Local)
+                                .format("%Y-%m-%d %H:%M")
+                                .to_string())
+                            .unwrap_or_default(),
+                        restored.hosts.len(),
+                        conflicts,
+                        restored.passwords.len()
+                    ));
+                    b.replace(Some(restored));
+                    opts.set_sensitive(true);
+                    restore_btn.set_sensitive(true);
+                }
+                Err(e) => {
+                    b.replace(None);
+                    opts.set_sensitive(false);
+                    restore_btn.set_sensitive(false);
+                    sum.set_text(&format!("Could not open backup: {:#}", e));
+                }
+            }
+        });
+    });
+
+    dialog.connect_response(move |d, res| {
+        if res != gtk4::ResponseType::Ok {
+            d.close();
+            return;
+        }
+        let Some(restored) = bundle.borrow_mut().take() else {
+            return;
+        };
+        let policy = CONFLICT_POLICIES
+            .get(conflict_dropdown.selected() as usize)
+            .copied()
+            .unwrap_or(ConflictPolicy::KeepExisting);
+        let existing = load_hosts().unwrap_or_default();
+        let plan = plan_restore(&restored, &existing, policy);
+        if let Err(e) = apply_restore(&plan, &restored, settings_check.is_active()) {
+            summary.set_text(&format!("Restore failed: {:#}", e));
+            return;
+        }
+        summary.set_text(&format!(
+            "Restored {} new and {} replaced host(s); {} skipped.",
+            plan.add.len(),
+            plan.replace.len(),
+            plan.skipped.len()
+        ));
+        d.set_response_sensitive(gtk4::ResponseType::Ok, false);
+        if passwords_check.is_active() && !plan.passwords.is_empty() {
+            glib::MainContext::default().spawn_local(async move {
+                for (alias, password) in plan.passwords {
+                    if let Err(e) = store_keyring_password(&alias, &password).await {
+                        tracing::error!("Failed to restore password for {}: {}", alias, e);
+                    }
+                }
+            });
+        }
+    });
+
+    dialog.present();
+}
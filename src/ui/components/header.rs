@@ -3,6 +3,8 @@ use gtk4::prelude::*;
 pub struct Header {
     pub container: gtk4::HeaderBar,
     pub add_btn: gtk4::Button,
+    pub import_btn: gtk4::Button,
+    pub lock_btn: gtk4::Button,
 }
 
 impl Header {
@@ -17,8 +19,25 @@ impl Header {
         add_btn.set_valign(gtk4::Align::Center);
         add_btn.set_tooltip_text(Some("Add Server"));
 
+        let import_btn = gtk4::Button::from_icon_name("document-open-symbolic");
+        import_btn.add_css_class("flat");
+        import_btn.set_valign(gtk4::Align::Center);
+        import_btn.set_tooltip_text(Some("Import Servers"));
+
+        let lock_btn = gtk4::Button::from_icon_name("system-lock-screen-symbolic");
+        lock_btn.add_css_class("flat");
+        lock_btn.set_valign(gtk4::Align::Center);
+        lock_btn.set_tooltip_text(Some("Lock Rustmius"));
+
         container.pack_start(&add_btn);
+        container.pack_start(&import_btn);
+        container.pack_end(&lock_btn);
 
-        Self { container, add_btn }
+        Self {
+            container,
+            add_btn,
+            import_btn,
+            lock_btn,
+        }
     }
 }
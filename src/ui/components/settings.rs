@@ -1,8 +1,30 @@
-use crate::config_observer::AppConfig;
+use crate::config_observer::{AppConfig, AppLockConfig, OutboundProxy, PasswordStorage, ProxyKind};
 use gtk4::prelude::*;
 
+const PROXY_KINDS: [ProxyKind; 3] = [ProxyKind::None, ProxyKind::Socks5, ProxyKind::HttpConnect];
+const PASSWORD_STORAGES: [PasswordStorage; 3] = [
+    PasswordStorage::Auto,
+    PasswordStorage::SecretService,
+    PasswordStorage::Vault,
+];
+/// Idle minutes before the app locks; 0 never does.
+const LOCK_IDLE_MINUTES: [u32; 6] = [0, 1, 5, 15, 30, 60];
+
 pub struct Settings {
     pub container: gtk4::Box,
+    font_button: gtk4::FontDialogButton,
+    scrollback_spinner: gtk4::SpinButton,
+    theme_dropdown: gtk4::DropDown,
+    refresh_dropdown: gtk4::DropDown,
+    confirm_switch: gtk4::Switch,
+    embedded_switch: gtk4::Switch,
+    proxy_dropdown: gtk4::DropDown,
+    proxy_host_entry: gtk4::Entry,
+    proxy_port_spinner: gtk4::SpinButton,
+    inventories_entry: gtk4::Entry,
+    storage_dropdown: gtk4::DropDown,
+    lock_switch: gtk4::Switch,
+    lock_idle_dropdown: gtk4::DropDown,
 }
 
 impl Settings {
@@ -73,6 +95,56 @@ impl Settings {
         let confirm_switch = gtk4::Switch::new();
         confirm_switch.set_active(config.confirm_tab_close);
 
+        let embedded_switch = gtk4::Switch::new();
+        embedded_switch.set_active(config.embedded_terminal);
+        embedded_switch.set_tooltip_text(Some(
+            "Connect terminal tabs with the same SSH client as the other tools instead of /usr/bin/ssh",
+        ));
+
+        let proxy_dropdown = gtk4::DropDown::from_strings(&["None", "SOCKS5", "HTTP CONNECT"]);
+        proxy_dropdown.set_selected(proxy_kind_index(config.outbound_proxy.kind));
+        let proxy_host_entry = gtk4::Entry::builder()
+            .placeholder_text("proxy.example.com")
+            .text(&config.outbound_proxy.host)
+            .build();
+        let proxy_port_adj = gtk4::Adjustment::new(
+            config.outbound_proxy.port as f64,
+            0.0,
+            65535.0,
+            1.0,
+            10.0,
+            0.0,
+        );
+        let proxy_port_spinner = gtk4::SpinButton::new(Some(&proxy_port_adj), 1.0, 0);
+
+        let inventories_entry = gtk4::Entry::builder()
+            .placeholder_text("~/infra/hosts.ini, ~/infra/prod.yml")
+            .text(config.ansible_inventories.join(", "))
+            .hexpand(true)
+            .build();
+        inventories_entry.set_tooltip_text(Some(
+            "Comma-separated Ansible inventory files or directories; their hosts are listed read-only",
+        ));
+
+        let storage_dropdown =
+            gtk4::DropDown::from_strings(&["Automatic", "Secret Service", "Encrypted file"]);
+        storage_dropdown.set_selected(password_storage_index(config.password_storage));
+        storage_dropdown.set_tooltip_text(Some(
+            "Automatic uses the desktop keyring when available and a master-password vault otherwise",
+        ));
+
+        let lock_switch = gtk4::Switch::new();
+        lock_switch.set_active(config.app_lock.is_enabled());
+        let lock_idle_dropdown = gtk4::DropDown::from_strings(&[
+            "Never",
+            "1 minute",
+            "5 minutes",
+            "15 minutes",
+            "30 minutes",
+            "1 hour",
+        ]);
+        lock_idle_dropdown.set_selected(lock_idle_index(config.app_lock.idle_minutes));
+
         let terminal_group = Self::settings_group("Terminal");
         Self::add_row(&terminal_group, "Font", font_button.clone().upcast());
         Self::add_row(
@@ -85,6 +157,11 @@ impl Settings {
             "Color Theme",
             theme_dropdown.clone().upcast(),
         );
+        Self::add_row(
+            &terminal_group,
+            "Built-in SSH client",
+            embedded_switch.clone().upcast(),
+        );
         content.append(&terminal_group);
 
         let monitor_group = Self::settings_group("System Monitor");
@@ -95,6 +172,32 @@ impl Settings {
         );
         content.append(&monitor_group);
 
+        let network_group = Self::settings_group("Network");
+        Self::add_row(
+            &network_group,
+            "Outbound Proxy",
+            proxy_dropdown.clone().upcast(),
+        );
+        Self::add_row(
+            &network_group,
+            "Proxy Host",
+            proxy_host_entry.clone().upcast(),
+        );
+        Self::add_row(
+            &network_group,
+            "Proxy Port",
+            proxy_port_spinner.clone().upcast(),
+        );
+        content.append(&network_group);
+
+        let hosts_group = Self::settings_group("Host Sources");
+        Self::add_row(
+            &hosts_group,
+            "Ansible Inventories",
+            inventories_entry.clone().upcast(),
+        );
+        content.append(&hosts_group);
+
         let ui_group = Self::settings_group("User Interface");
         Self::add_row(
             &ui_group,
@@ -103,13 +206,64 @@ impl Settings {
         );
         content.append(&ui_group);
 
+        let export_button = gtk4::Button::with_label("Export…");
+        export_button.connect_clicked(|btn| {
+            if let Some(window) = btn.root().and_then(|r| r.downcast::<gtk4::Window>().ok()) {
+                crate::ui::backup_dialog::show_export_dialog(&window);
+            }
+        });
+        let restore_button = gtk4::Button::with_label("Restore…");
+        restore_button.connect_clicked(|btn| {
+            if let Some(window) = btn.root().and_then(|r| r.downcast::<gtk4::Window>().ok()) {
+                crate::ui::backup_dialog::show_restore_dialog(&window);
+            }
+        });
+        let passwords_group = Self::settings_group("Passwords");
+        Self::add_row(
+            &passwords_group,
+            "Password Storage",
+            storage_dropdown.clone().upcast(),
+        );
+        Self::add_row(
+            &passwords_group,
+            "Lock with master password",
+            lock_switch.clone().upcast(),
+        );
+        Self::add_row(
+            &passwords_group,
+            "Lock when idle for",
+            lock_idle_dropdown.clone().upcast(),
+        );
+        content.append(&passwords_group);
+
+        let backup_group = Self::settings_group("Backup");
+        Self::add_row(
+            &backup_group,
+            "Encrypted backup of hosts, settings and passwords",
+            export_button.upcast(),
+        );
+        Self::add_row(
+            &backup_group,
+            "Restore from a backup file",
+            restore_button.upcast(),
+        );
+        content.append(&backup_group);
+
         let r_drop = refresh_dropdown.clone();
         let f_btn = font_button.clone();
         let s_spin = scrollback_spinner.clone();
         let t_drop = theme_dropdown.clone();
         let c_switch = confirm_switch.clone();
+        let e_switch = embedded_switch.clone();
+        let p_drop = proxy_dropdown.clone();
+        let p_host = proxy_host_entry.clone();
+        let p_port = proxy_port_spinner.clone();
+        let inv_entry = inventories_entry.clone();
+        let storage_drop = storage_dropdown.clone();
+        let idle_drop = lock_idle_dropdown.clone();
 
         let save_config = move || {
+            let current = crate::config_observer::load_app_config().unwrap_or_default();
             let new_config = AppConfig {
                 monitor_refresh_rate: r_drop.selected(),
                 terminal_font: f_btn
@@ -121,6 +275,28 @@ impl Settings {
                     .name
                     .to_string(),
                 confirm_tab_close: c_switch.is_active(),
+                embedded_terminal: e_switch.is_active(),
+                outbound_proxy: OutboundProxy {
+                    kind: PROXY_KINDS
+                        .get(p_drop.selected() as usize)
+                        .copied()
+                        .unwrap_or_default(),
+                    host: p_host.text().trim().to_string(),
+                    port: p_port.value() as u16,
+                },
+                ansible_inventories: parse_inventory_list(&inv_entry.text()),
+                password_storage: PASSWORD_STORAGES
+                    .get(storage_drop.selected() as usize)
+                    .copied()
+                    .unwrap_or_default(),
+                app_lock: AppLockConfig {
+                    idle_minutes: LOCK_IDLE_MINUTES
+                        .get(idle_drop.selected() as usize)
+                        .copied()
+                        .unwrap_or_default(),
+                    ..current.app_lock.clone()
+                },
+                ..current
             };
             let _ = crate::config_observer::save_app_config(&new_config);
         };
@@ -143,6 +319,67 @@ impl Settings {
             s5();
         });
 
+        let s6 = save_fn.clone();
+        embedded_switch.connect_active_notify(move |_| {
+            s6();
+        });
+
+        let s7 = save_fn.clone();
+        proxy_dropdown.connect_selected_notify(move |_| {
+            s7();
+        });
+        let s8 = save_fn.clone();
+        proxy_host_entry.connect_changed(move |_| {
+            s8();
+        });
+        let s9 = save_fn.clone();
+        proxy_port_spinner.connect_value_changed(move |_| {
+            s9();
+        });
+
+        let s10 = save_fn.clone();
+        inventories_entry.connect_changed(move |_| {
+            s10();
+        });
+
+        let s11 = save_fn.clone();
+        storage_dropdown.connect_selected_notify(move |dd| {
+            s11();
+            if let Some(window) = dd.root().and_then(|r| r.downcast::<gtk4::Window>().ok()) {
+                crate::ui::vault_dialog::unlock_vault_if_needed(&window);
+            }
+        });
+
+        let s12 = save_fn.clone();
+        lock_idle_dropdown.connect_selected_notify(move |_| {
+            s12();
+        });
+
+        lock_switch.connect_active_notify(|switch| {
+            let mut config = crate::config_observer::load_app_config().unwrap_or_default();
+            if switch.is_active() == config.app_lock.is_enabled() {
+                return;
+            }
+            if !switch.is_active() {
+                config.app_lock.password_hash = None;
+                let _ = crate::config_observer::save_app_config(&config);
+                return;
+            }
+            let Some(window) = switch
+                .root()
+                .and_then(|r| r.downcast::<gtk4::Window>().ok())
+            else {
+                switch.set_active(false);
+                return;
+            };
+            let switch = switch.clone();
+            crate::ui::app_lock::show_set_password_dialog(&window, move |enabled| {
+                if !enabled {
+                    switch.set_active(false);
+                }
+            });
+        });
+
         let s4 = save_fn.clone();
         let nb = notebook.clone();
         theme_dropdown.connect_selected_notify(move |dd| {
@@ -156,7 +393,78 @@ impl Settings {
         scrolled.set_child(Some(&content));
         container.append(&scrolled);
 
-        Self { container }
+        Self {
+            container,
+            font_button,
+            scrollback_spinner,
+            theme_dropdown,
+            refresh_dropdown,
+            confirm_switch,
+            embedded_switch,
+            proxy_dropdown,
+            proxy_host_entry,
+            proxy_port_spinner,
+            inventories_entry,
+            storage_dropdown,
+            lock_switch,
+            lock_idle_dropdown,
+        }
+    }
+
+    /// Shows `config` after it was changed outside the app. Only widgets whose
+    /// value differs are touched, so saving it back doesn't loop.
+    pub fn apply_config(&self, config: &AppConfig) {
+        let font = gtk4::pango::FontDescription::from_string(&config.terminal_font);
+        if self.font_button.font_desc().as_ref() != Some(&font) {
+            self.font_button.set_font_desc(&font);
+        }
+        if self.scrollback_spinner.value() as u32 != config.terminal_scrollback {
+            self.scrollback_spinner
+                .set_value(config.terminal_scrollback as f64);
+        }
+        if let Some(theme) = crate::ui::theme::THEMES
+            .iter()
+            .position(|t| t.name == config.terminal_theme)
+            && self.theme_dropdown.selected() != theme as u32
+        {
+            self.theme_dropdown.set_selected(theme as u32);
+        }
+        if self.refresh_dropdown.selected() != config.monitor_refresh_rate {
+            self.refresh_dropdown
+                .set_selected(config.monitor_refresh_rate);
+        }
+        if self.confirm_switch.is_active() != config.confirm_tab_close {
+            self.confirm_switch.set_active(config.confirm_tab_close);
+        }
+        if self.embedded_switch.is_active() != config.embedded_terminal {
+            self.embedded_switch.set_active(config.embedded_terminal);
+        }
+        let proxy_kind = proxy_kind_index(config.outbound_proxy.kind);
+        if self.proxy_dropdown.selected() != proxy_kind {
+            self.proxy_dropdown.set_selected(proxy_kind);
+        }
+        if self.proxy_host_entry.text() != config.outbound_proxy.host {
+            self.proxy_host_entry.set_text(&config.outbound_proxy.host);
+        }
+        if self.proxy_port_spinner.value() as u16 != config.outbound_proxy.port {
+            self.proxy_port_spinner
+                .set_value(config.outbound_proxy.port as f64);
+        }
+        if parse_inventory_list(&self.inventories_entry.text()) != config.ansible_inventories {
+            self.inventories_entry
+                .set_text(&config.ansible_inventories.join(", "));
+        }
+        let storage = password_storage_index(config.password_storage);
+        if self.storage_dropdown.selected() != storage {
+            self.storage_dropdown.set_selected(storage);
+        }
+        if self.lock_switch.is_active() != config.app_lock.is_enabled() {
+            self.lock_switch.set_active(config.app_lock.is_enabled());
+        }
+        let lock_idle = lock_idle_index(config.app_lock.idle_minutes);
+        if self.lock_idle_dropdown.selected() != lock_idle {
+            self.lock_idle_dropdown.set_selected(lock_idle);
+        }
     }
 
     fn settings_group(title: &str) -> gtk4::Box {
@@ -183,3 +491,30 @@ impl Settings {
         group.append(&row);
     }
 }
+
+fn proxy_kind_index(kind: ProxyKind) -> u32 {
+    PROXY_KINDS.iter().position(|k| *k == kind).unwrap_or(0) as u32
+}
+
+fn password_storage_index(storage: PasswordStorage) -> u32 {
+    PASSWORD_STORAGES
+        .iter()
+        .position(|s| *s == storage)
+        .unwrap_or(0) as u32
+}
+
+/// The closest listed idle time at or above `minutes`.
+fn lock_idle_index(minutes: u32) -> u32 {
+    LOCK_IDLE_MINUTES
+        .iter()
+        .position(|&listed| listed >= minutes)
+        .unwrap_or(LOCK_IDLE_MINUTES.len() - 1) as u32
+}
+
+fn parse_inventory_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,122 @@
+use crate::config_observer::{
+    AppConfig, ansible_inventory_paths, get_app_config_path, get_host_metadata_path,
+    load_app_config, load_hosts, refresh_app_config, refresh_hosts, ssh_config_watch_paths,
+};
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Quiet period after the last file event before reloading, so editors that
+/// write in several steps (or Ansible touching many files) cause one reload.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches `~/.ssh/config`, its includes, the Ansible inventories, `hosts.json`
+/// and `config.json`, reloading the caches and notifying the UI when their
+/// parsed contents change.
+#[derive(Clone)]
+pub struct ConfigWatcher {
+    inner: Rc<ConfigWatcherInner>,
+}
+
+struct ConfigWatcherInner {
+    monitors: RefCell<Vec<gio::FileMonitor>>,
+    pending: RefCell<Option<glib::SourceId>>,
+    on_hosts_changed: Box<dyn Fn()>,
+    on_app_config_changed: Box<dyn Fn(&AppConfig)>,
+}
+
+impl ConfigWatcher {
+    pub fn new(
+        on_hosts_changed: impl Fn() + 'static,
+        on_app_config_changed: impl Fn(&AppConfig) + 'static,
+    ) -> Self {
+        let watcher = Self {
+            inner: Rc::new(ConfigWatcherInner {
+                monitors: RefCell::new(Vec::new()),
+                pending: RefCell::new(None),
+                on_hosts_changed: Box::new(on_hosts_changed),
+                on_app_config_changed: Box::new(on_app_config_changed),
+            }),
+        };
+        watcher.watch();
+        watcher
+    }
+
+    /// (Re)creates the monitors; the set of included files may have changed.
+    fn watch(&self) {
+        let mut paths: Vec<PathBuf> = ssh_config_watch_paths();
+        paths.extend(ansible_inventory_paths());
+        paths.extend(get_host_metadata_path());
+        paths.extend(get_app_config_path());
+
+        let mut monitors = Vec::with_capacity(paths.len());
+        for path in paths {
+            let file = gio::File::for_path(&path);
+            let monitor = if path.is_dir() {
+                file.monitor_directory(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)
+            } else {
+                file.monitor_file(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)
+            };
+            match monitor {
+                Ok(monitor) => {
+                    let weak = Rc::downgrade(&self.inner);
+                    monitor.connect_changed(move |_, _, _, event| {
+                        if event == gio::FileMonitorEvent::AttributeChanged {
+                            return;
+                        }
+                        if let Some(inner) = weak.upgrade() {
+                            Self { inner }.schedule_reload();
+                        }
+                    });
+                    monitors.push(monitor);
+                }
+                Err(e) => tracing::warn!("Failed to watch {}: {}", path.display(), e),
+            }
+        }
+        for old in self.inner.monitors.replace(monitors) {
+            old.cancel();
+        }
+    }
+
+    fn schedule_reload(&self) {
+        if let Some(source) = self.inner.pending.take() {
+            source.remove();
+        }
+        let this = self.clone();
+        let source = glib::timeout_add_local_once(RELOAD_DEBOUNCE, move || {
+            this.inner.pending.take();
+            this.reload();
+        });
+        self.inner.pending.replace(Some(source));
+    }
+
+    /// Re-parses both configs and notifies only for the ones whose contents
+    /// differ from the cache, so the app's own writes don't trigger a refresh.
+    fn reload(&self) {
+        // Config first: the host list depends on its inventory paths.
+        let old_config = load_app_config().ok();
+        match refresh_app_config() {
+            Ok(config) if old_config.as_ref() != Some(&config) => {
+                tracing::info!("App config changed on disk, reloading settings");
+                (self.inner.on_app_config_changed)(&config);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("Failed to reload app config: {}", e),
+        }
+
+        let old_hosts = load_hosts().unwrap_or_default();
+        match refresh_hosts() {
+            Ok(hosts) if hosts != old_hosts => {
+                tracing::info!("SSH config changed on disk, reloading hosts");
+                (self.inner.on_hosts_changed)();
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("Failed to reload SSH config: {}", e),
+        }
+
+        self.watch();
+    }
+}
@@ -0,0 +1,131 @@
+#![allow(deprecated)]
+use crate::config_observer::SshHost;
+use crate::engines::diagnostics::{DiagnosticReport, StepStatus, diagnose_connection};
+use gtk4::glib;
+use gtk4::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Runs the connection diagnostics for a host and shows the step-by-step report.
+pub fn show_diagnostics_dialog(parent: &gtk4::Window, host: SshHost, password: Option<String>) {
+    let dialog = gtk4::Dialog::builder()
+        .transient_for(parent)
+        .modal(true)
+        .title(format!("Connection Test — {}", host.alias))
+        .default_width(520)
+        .default_height(480)
+        .build();
+
+    let content = dialog.content_area();
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    content.set_spacing(12);
+
+    let summary = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+    let spinner = gtk4::Spinner::builder().spinning(true).build();
+    let summary_label = gtk4::Label::builder()
+        .label(format!(
+            "Testing {}@{}…",
+            host.user.as_deref().unwrap_or("root"),
+            host.hostname
+        ))
+        .halign(gtk4::Align::Start)
+        .css_classes(vec!["bold".to_string()])
+        .build();
+    summary.append(&spinner);
+    summary.append(&summary_label);
+    content.append(&summary);
+
+    let list_box = gtk4::ListBox::new();
+    list_box.set_selection_mode(gtk4::SelectionMode::None);
+    list_box.add_css_class("boxed-list");
+    let scrolled = gtk4::ScrolledWindow::builder()
+        .child(&list_box)
+        .vexpand(true)
+        .hscrollbar_policy(gtk4::PolicyType::Never)
+        .build();
+    content.append(&scrolled);
+
+    let copy_button = dialog.add_button("Copy Report", gtk4::ResponseType::Apply);
+    copy_button.set_sensitive(false);
+    dialog.add_button("Close", gtk4::ResponseType::Close);
+
+    let report_text = Rc::new(RefCell::new(String::new()));
+    let report_copy = report_text.clone();
+    dialog.connect_response(move |d, res| {
+        if res == gtk4::ResponseType::Apply {
+            d.clipboard().set_text(&report_copy.borrow());
+            return;
+        }
+        d.close();
+    });
+    dialog.present();
+
+    glib::MainContext::default().spawn_local(async move {
+        let report = diagnose_connection(&host, password.as_deref()).await;
+        spinner.set_spinning(false);
+        spinner.set_visible(false);
+        if report.succeeded() {
+            summary_label.set_label("Connection succeeded");
+        } else {
+            summary_label.set_label("Connection failed");
+            summary_label.add_css_class("error");
+        }
+        fill_report(&list_box, &report);
+        *report_text.borrow_mut() = report.to_string();
+        copy_button.set_sensitive(true);
+    });
+}
+
+fn fill_report(list_box: &gtk4::ListBox, report: &DiagnosticReport) {
+    for step in &report.steps {
+        let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 12);
+        row.add_css_class("list-row-content");
+
+        let (icon_name, css_class) = match step.status {
+            StepStatus::Passed => ("emblem-ok-symbolic", "success"),
+            StepStatus::Warning => ("dialog-warning-symbolic", "warning"),
+            StepStatus::Failed => ("dialog-error-symbolic", "error"),
+        };
+        let icon = gtk4::Image::from_icon_name(icon_name);
+        icon.add_css_class(css_class);
+        icon.set_valign(gtk4::Align::Start);
+        row.append(&icon);
+
+        let text_box = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+        text_box.set_hexpand(true);
+        text_box.append(
+            &gtk4::Label::builder()
+                .label(&step.title)
+                .halign(gtk4::Align::Start)
+                .wrap(true)
+                .css_classes(vec!["bold".to_string()])
+                .build(),
+        );
+        if !step.detail.is_empty() {
+            text_box.append(
+                &gtk4::Label::builder()
+                    .label(&step.detail)
+                    .halign(gtk4::Align::Start)
+                    .wrap(true)
+                    .selectable(true)
+                    .css_classes(vec!["caption".to_string(), "dim-label".to_string()])
+                    .build(),
+            );
+        }
+        row.append(&text_box);
+
+        if let Some(elapsed) = step.elapsed {
+            row.append(
+                &gtk4::Label::builder()
+                    .label(format!("{} ms", elapsed.as_millis()))
+                    .valign(gtk4::Align::Start)
+                    .css_classes(vec!["caption".to_string(), "dim-label".to_string()])
+                    .build(),
+            );
+        }
+        list_box.append(&row);
+    }
+}
@@ -1,5 +1,8 @@
 use crate::config_observer::SshHost;
-use crate::engines::docker::{get_docker_stats, list_docker_items, perform_docker_action};
+use crate::engines::docker::{
+    get_docker_stats, list_docker_items, perform_docker_action, stream_container_logs,
+};
+use crate::engines::exec::OutputLine;
 use gtk4::glib;
 use gtk4::prelude::*;
 
@@ -327,20 +330,36 @@ impl DockerManager {
                             let rb_toggle = rb_inner.clone();
                             let cmd_str = action_cmd.to_string();
 
-                            toggle_btn.connect_clicked(move |_| {
+                            toggle_btn.connect_clicked(move |btn| {
                                 let h_c = h_toggle.clone();
                                 let p_c = p_toggle.clone();
                                 let n_c = n_toggle.clone();
                                 let rb_c = rb_toggle.clone();
                                 let c_str = cmd_str.clone();
+                                let btn = btn.clone();
                                 glib::MainContext::default().spawn_local(async move {
-                                    let _ =
+                                    if let Err(e) =
                                         perform_docker_action(&h_c, p_c.as_deref(), &c_str, &n_c)
-                                            .await;
+                                            .await
+                                    {
+                                        show_action_error(&btn, &e);
+                                    }
                                     rb_c.emit_clicked();
                                 });
                             });
                             actions.append(&toggle_btn);
+
+                            let logs_btn =
+                                gtk4::Button::from_icon_name("utilities-terminal-symbolic");
+                            logs_btn.add_css_class("flat");
+                            logs_btn.set_tooltip_text(Some("Show Logs"));
+                            let h_logs = h.clone();
+                            let p_logs = p.clone();
+                            let n_logs = item_name.clone();
+                            logs_btn.connect_clicked(move |btn| {
+                                show_logs_window(btn, h_logs.clone(), p_logs.clone(), &n_logs);
+                            });
+                            actions.append(&logs_btn);
                         }
 
                         let delete_btn = gtk4::Button::from_icon_name("user-trash-symbolic");
@@ -357,15 +376,19 @@ impl DockerManager {
                         let n_del = item_name.clone();
                         let rb_del = rb_inner.clone();
                         let is_c_del = is_container;
-                        delete_btn.connect_clicked(move |_| {
+                        delete_btn.connect_clicked(move |btn| {
                             let h_c = h_del.clone();
                             let p_c = p_del.clone();
                             let n_c = n_del.clone();
                             let rb_c = rb_del.clone();
+                            let btn = btn.clone();
                             glib::MainContext::default().spawn_local(async move {
                                 let sub_cmd = if is_c_del { "rm -f" } else { "rmi" };
-                                let _ = perform_docker_action(&h_c, p_c.as_deref(), sub_cmd, &n_c)
-                                    .await;
+                                if let Err(e) =
+                                    perform_docker_action(&h_c, p_c.as_deref(), sub_cmd, &n_c).await
+                                {
+                                    show_action_error(&btn, &e);
+                                }
                                 rb_c.emit_clicked();
                             });
                         });
@@ -457,3 +480,102 @@ impl DockerManager {
         None
     }
 }
+
+fn show_action_error(widget: &impl IsA<gtk4::Widget>, err: &anyhow::Error) {
+    let dialog = gtk4::AlertDialog::builder()
+        .modal(true)
+        .message("Docker action failed")
+        .detail(format!("{:#}", err))
+        .build();
+    dialog.show(widget.root().and_downcast::<gtk4::Window>().as_ref());
+}
+
+/// Opens a window following the logs of a container; closing it stops the stream.
+fn show_logs_window(
+    widget: &impl IsA<gtk4::Widget>,
+    host: SshHost,
+    password: Option<String>,
+    container: &str,
+) {
+    let window = gtk4::Window::builder()
+        .title(format!("Logs of {}", container))
+        .default_width(800)
+        .default_height(500)
+        .build();
+    if let Some(parent) = widget.root().and_downcast::<gtk4::Window>() {
+        window.set_transient_for(Some(&parent));
+    }
+
+    let buffer = gtk4::TextBuffer::new(None);
+    let error_tag = buffer.create_tag(Some("stderr"), &[("foreground", &"#e01b24")]);
+    let text_view = gtk4::TextView::builder()
+        .buffer(&buffer)
+        .editable(false)
+        .monospace(true)
+        .build();
+    let scrolled = gtk4::ScrolledWindow::builder()
+        .child(&text_view)
+        .vexpand(true)
+        .build();
+    let status = gtk4::Label::builder()
+        .label("Connecting…")
+        .halign(gtk4::Align::Start)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(6)
+        .margin_bottom(6)
+        .css_classes(vec!["caption".to_string(), "dim-label".to_string()])
+        .build();
+    let content = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    content.append(&scrolled);
+    content.append(&status);
+    window.set_child(Some(&content));
+    window.present();
+
+    let container = container.to_string();
+    let window_weak = window.downgrade();
+    glib::MainContext::default().spawn_local(async move {
+        let mut stream = match stream_container_logs(&host, password.as_deref(), &container).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                status.set_label(&format!("Failed to follow logs: {:#}", e));
+                status.add_css_class("error");
+                return;
+            }
+        };
+        status.set_label("Following logs");
+        let cancel = stream.cancel.clone();
+        match window_weak.upgrade() {
+            Some(window) => {
+                window.connect_close_request(move |_| {
+                    cancel.cancel();
+                    glib::Propagation::Proceed
+                });
+            }
+            // Closed while connecting.
+            None => cancel.cancel(),
+        }
+
+        while let Some(line) = stream.lines.recv().await {
+            let mut end = buffer.end_iter();
+            match line {
+                OutputLine::Stdout(text) => buffer.insert(&mut end, &format!("{}\n", text)),
+                OutputLine::Stderr(text) => {
+                    if let Some(tag) = &error_tag {
+                        buffer.insert_with_tags(&mut end, &format!("{}\n", text), &[tag]);
+                    }
+                }
+            }
+            text_view.scroll_to_iter(&mut buffer.end_iter(), 0.0, false, 0.0, 1.0);
+        }
+
+        match stream.wait().await {
+            Ok(exit) => status.set_label(&format!(
+                "Stopped after {}s (exit status {})",
+                exit.duration.as_secs(),
+                exit.exit_status
+            )),
+            Err(e) => status.set_label(&format!("Stopped: {:#}", e)),
+        }
+    });
+}
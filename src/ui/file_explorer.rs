@@ -704,7 +704,7 @@ fn file_icon(file: &RemoteFile) -> gio::Icon {
     gio::content_type_get_icon(&content_type)
 }
 
-fn format_file_size(bytes: u64) -> String {
+pub(crate) fn format_file_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = 1024 * 1024;
     const GB: u64 = 1024 * 1024 * 1024;
@@ -0,0 +1,72 @@
+use crate::engines::known_hosts::{HostKeyPrompt, known_hosts_name, set_host_key_prompter};
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use std::cell::RefCell;
+use tokio::sync::oneshot;
+
+/// Routes host key questions from the engine threads to a dialog on the GTK main loop.
+pub fn install_host_key_prompter() {
+    set_host_key_prompter(|prompt, reply| {
+        glib::MainContext::default().invoke(move || show_host_key_dialog(prompt, reply));
+    });
+}
+
+fn active_window() -> Option<gtk4::Window> {
+    gio::Application::default()
+        .and_downcast::<gtk4::Application>()
+        .and_then(|app| app.active_window())
+}
+
+fn show_host_key_dialog(prompt: HostKeyPrompt, reply: oneshot::Sender<bool>) {
+    let parent = active_window();
+    match prompt {
+        HostKeyPrompt::Unknown(info) => {
+            let dialog = gtk4::AlertDialog::builder()
+                .modal(true)
+                .message(format!("Trust host {}?", info.hostname))
+                .detail(format!(
+                    "The authenticity of host '{}' (port {}) can't be established.\n\n\
+                     {} key fingerprint:\n{}\n\n\
+                     Only continue if this matches the fingerprint you expect. \
+                     The key will be added to ~/.ssh/known_hosts.",
+                    info.hostname, info.port, info.key_type, info.fingerprint
+                ))
+                .buttons(vec!["Cancel", "Trust and Connect"])
+                .cancel_button(0)
+                .default_button(0)
+                .build();
+
+            let reply = RefCell::new(Some(reply));
+            dialog.choose(parent.as_ref(), None::<&gio::Cancellable>, move |res| {
+                let accepted = matches!(res, Ok(1));
+                if let Some(tx) = reply.borrow_mut().take() {
+                    let _ = tx.send(accepted);
+                }
+            });
+        }
+        HostKeyPrompt::Changed(info) => {
+            let dialog = gtk4::AlertDialog::builder()
+                .modal(true)
+                .message("WARNING: Remote host identification has changed!")
+                .detail(format!(
+                    "The host key presented by '{}' (port {}) does not match the one \
+                     recorded in ~/.ssh/known_hosts. Someone could be eavesdropping on \
+                     you right now (man-in-the-middle attack), or the host key has just \
+                     been changed.\n\n\
+                     {} key fingerprint sent by the remote host:\n{}\n\n\
+                     The connection has been refused. If the change is expected, remove \
+                     the old entry with: ssh-keygen -R {}",
+                    info.hostname,
+                    info.port,
+                    info.key_type,
+                    info.fingerprint,
+                    known_hosts_name(&info.hostname, info.port)
+                ))
+                .buttons(vec!["Close"])
+                .default_button(0)
+                .build();
+            dialog.show(parent.as_ref());
+            let _ = reply.send(false);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+#![allow(deprecated)]
+use crate::import::{ImportSource, ImportedHost, import_hosts};
+use crate::ui::add_server_dialog::is_duplicate_alias;
+use gtk4::gio;
+use gtk4::prelude::*;
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Asks for a file or directory to import from, previews the hosts found and
+/// passes the ones left checked to `on_import`. Hosts whose alias is already in
+/// `existing_aliases` (lowercased) or earlier in the import can't be selected.
+pub fn show_import_dialog<F>(parent: &gtk4::Window, existing_aliases: Vec<String>, on_import: F)
+where
+    F: Fn(Vec<ImportedHost>) + 'static,
+{
+    let dialog = gtk4::Dialog::builder()
+        .transient_for(parent)
+        .modal(true)
+        .title("Import Servers")
+        .default_width(520)
+        .default_height(480)
+        .build();
+
+    let content = dialog.content_area();
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    content.set_spacing(12);
+
+    let labels: Vec<&str> = ImportSource::ALL.iter().map(|s| s.label()).collect();
+    let source_dropdown = gtk4::DropDown::from_strings(&labels);
+    let choose_button = gtk4::Button::with_label("Choose…");
+    let source_row = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+    source_dropdown.set_hexpand(true);
+    source_row.append(&source_dropdown);
+    source_row.append(&choose_button);
+    content.append(
+        &gtk4::Label::builder()
+            .label("Import from")
+            .halign(gtk4::Align::Start)
+            .build(),
+    );
+    content.append(&source_row);
+
+    let status_label = gtk4::Label::builder()
+        .label("Choose a file or folder to preview its hosts.")
+        .halign(gtk4::Align::Start)
+        .wrap(true)
+        .css_classes(vec!["dim-label".to_string()])
+        .build();
+    content.append(&status_label);
+
+    let list_box = gtk4::ListBox::new();
+    list_box.set_selection_mode(gtk4::SelectionMode::None);
+    list_box.add_css_class("boxed-list");
+    let scrolled = gtk4::ScrolledWindow::builder()
+        .child(&list_box)
+        .vexpand(true)
+        .hscrollbar_policy(gtk4::PolicyType::Never)
+        .build();
+    content.append(&scrolled);
+
+    let import_button = dialog.add_button("Import", gtk4::ResponseType::Ok);
+    import_button.set_sensitive(false);
+    dialog.add_button("Cancel", gtk4::ResponseType::Cancel);
+
+    let preview: Rc<RefCell<Vec<(ImportedHost, gtk4::CheckButton)>>> =
+        Rc::new(RefCell::new(Vec::new()));
+    let existing_aliases = Rc::new(existing_aliases);
+
+    let load_preview = {
+        let preview = preview.clone();
+        let list_box = list_box.clone();
+        let status_label = status_label.clone();
+        let import_button = import_button.clone();
+        move |source: ImportSource, path: &Path| {
+            while let Some(row) = list_box.first_child() {
+                list_box.remove(&row);
+            }
+            preview.borrow_mut().clear();
+            import_button.set_sensitive(false);
+
+            let hosts = match import_hosts(source, path) {
+                Ok(hosts) => hosts,
+                Err(e) => {
+                    status_label.set_text(&format!("Import failed: {:#}", e));
+                    return;
+                }
+            };
+
+            let mut seen: Vec<String> = existing_aliases.as_ref().clone();
+            let mut duplicates = 0;
+            for imported in hosts {
+                let duplicate = is_duplicate_alias(&seen, &imported.host.alias);
+                seen.push(imported.host.alias.to_lowercase());
+                list_box.append(&preview_row(&imported, duplicate, &preview, &import_button));
+                if duplicate {
+                    duplicates += 1;
+                }
+            }
+            let found = preview.borrow().len() + duplicates;
+            status_label.set_text(&match (found, duplicates) {
+                (0, _) => format!("No SSH hosts found in {}.", path.display()),
+                (n, 0) => format!("Found {} host(s).", n),
+                (n, d) => format!(
+                    "Found {} host(s); {} already exist and will be skipped.",
+                    n, d
+                ),
+            });
+            import_button.set_sensitive(!preview.borrow().is_empty());
+        }
+    };
+    let load_preview = Rc::new(load_preview);
+
+    let dd = source_dropdown.clone();
+    let dialog_clone = dialog.clone();
+    choose_button.connect_clicked(move |_| {
+        let source = ImportSource::ALL
+            .get(dd.selected() as usize)
+            .copied()
+            .unwrap_or(ImportSource::Csv);
+        let file_dialog = gtk4::FileDialog::builder().title(source.label()).build();
+        if let Some(dir) = source.default_location().filter(|d| d.is_dir()) {
+            file_dialog.set_initial_folder(Some(&gio::File::for_path(dir)));
+        }
+        let load_preview = load_preview.clone();
+        let on_chosen = move |res: Result<gio::File, gtk4::glib::Error>| {
+            if let Ok(file) = res
+                && let Some(path) = file.path()
+            {
+                load_preview(source, &path);
+            }
+        };
+        if source.reads_directory() {
+            file_dialog.select_folder(Some(&dialog_clone), gio::Cancellable::NONE, on_chosen);
+        } else {
+            file_dialog.open(Some(&dialog_clone), gio::Cancellable::NONE, on_chosen);
+        }
+    });
+
+    dialog.connect_response(move |d, res| {
+        if res == gtk4::ResponseType::Ok {
+            let selected: Vec<ImportedHost> = preview
+                .borrow()
+                .iter()
+                .filter(|(_, check)| check.is_active())
+                .map(|(imported, _)| imported.clone())
+                .collect();
+            if !selected.is_empty() {
+                on_import(selected);
+            }
+        }
+        d.close();
+    });
+
+    dialog.present();
+}
+
+/// A preview line; selectable hosts are added to `preview` with their check box.
+fn preview_row(
+    imported: &ImportedHost,
+    duplicate: bool,
+    preview: &Rc<RefCell<Vec<(ImportedHost, gtk4::CheckButton)>>>,
+    import_button: &gtk4::Widget,
+) -> gtk4::Box {
+    let host = &imported.host;
+    let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 10);
+    row.set_margin_top(6);
+    row.set_margin_bottom(6);
+    row.set_margin_start(8);
+    row.set_margin_end(8);
+
+    let check = gtk4::CheckButton::builder()
+        .active(!duplicate)
+        .sensitive(!duplicate)
+        .build();
+    row.append(&check);
+
+    let text_box = gtk4::Box::new(gtk4::Orientation::Vertical, 2);
+    text_box.set_hexpand(true);
+    text_box.append(
+        &gtk4::Label::builder()
+            .label(&host.alias)
+            .halign(gtk4::Align::Start)
+            .css_classes(vec!["bold".to_string()])
+            .build(),
+    );
+    let mut details = format!(
+        "{}@{}:{}",
+        host.user.as_deref().unwrap_or("root"),
+        host.hostname,
+        host.port.unwrap_or(22)
+    );
+    if let Some(folder) = &host.folder {
+        details.push_str(&format!(" · {}", folder));
+    }
+    if imported.password.is_some() {
+        details.push_str(" · password");
+    }
+    text_box.append(
+        &gtk4::Label::builder()
+            .label(&details)
+            .halign(gtk4::Align::Start)
+            .css_classes(vec!["dim-label".to_string(), "caption".to_string()])
+            .build(),
+    );
+    row.append(&text_box);
+
+    if duplicate {
+        let label = gtk4::Label::new(Some("Alias already exists"));
+        label.add_css_class("error");
+        label.add_css_class("caption");
+        row.append(&label);
+    } else {
+        let preview_ref = preview.clone();
+        let import_button = import_button.clone();
+        check.connect_toggled(move |_| {
+            let any = preview_ref.borrow().iter().any(|(_, c)| c.is_active());
+            import_button.set_sensitive(any);
+        });
+        preview.borrow_mut().push((imported.clone(), check));
+    }
+    row
+}
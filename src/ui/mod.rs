@@ -1,12 +1,21 @@
 pub mod add_server_dialog;
+pub mod app_lock;
+pub mod auth_dialog;
+pub mod backup_dialog;
 pub mod components;
+pub mod config_watcher;
+pub mod diagnostics_dialog;
 pub mod docker;
 pub mod file_explorer;
+pub mod host_key_dialog;
+pub mod import_dialog;
 pub mod monitor;
 pub mod server_list;
 pub mod ssh_keys;
 pub mod style;
 pub mod theme;
+pub mod tunnels;
+pub mod vault_dialog;
 pub mod window;
 
 use gtk4::prelude::*;
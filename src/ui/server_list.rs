@@ -1,42 +1,110 @@
-use crate::config_observer::{SshHost, load_hosts};
+use crate::config_observer::{
+    HostUsage, ServerSort, SshHost, load_app_config, load_host_usage, load_hosts, save_app_config,
+};
 use gtk4::glib;
 use gtk4::prelude::*;
+use std::collections::BTreeMap;
+
+/// Number of `tag-color-N` classes in the stylesheet.
+const TAG_COLORS: u32 = 6;
 
 pub enum ServerAction {
     Connect(SshHost, Option<String>),
+    Test(SshHost),
     Edit(SshHost),
     Delete(SshHost),
 }
 
 pub struct ServerList {
-    pub container: gtk4::ScrolledWindow,
-    pub flow_box: gtk4::FlowBox,
+    pub container: gtk4::Box,
+    search_entry: gtk4::SearchEntry,
+    sort_dropdown: gtk4::DropDown,
+    groups_box: gtk4::Box,
 }
 
+const SORTS: [ServerSort; 4] = [
+    ServerSort::ConfigOrder,
+    ServerSort::Name,
+    ServerSort::LastConnected,
+    ServerSort::MostUsed,
+];
+
 impl ServerList {
     pub fn new<F>(on_action: F) -> Self
     where
         F: Fn(ServerAction) + 'static + Clone,
     {
+        let container = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+
+        let toolbar = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+        toolbar.add_css_class("server-list-toolbar");
+        let search_entry = gtk4::SearchEntry::builder()
+            .placeholder_text("Search hosts by alias, hostname, user or tag")
+            .hexpand(true)
+            .build();
+        search_entry.set_key_capture_widget(Some(&container));
+        let sort_dropdown =
+            gtk4::DropDown::from_strings(&["Config order", "Name", "Last connected", "Most used"]);
+        sort_dropdown.set_tooltip_text(Some("Sort hosts"));
+        let sort = load_app_config()
+            .map(|config| config.server_sort)
+            .unwrap_or_default();
+        sort_dropdown.set_selected(SORTS.iter().position(|s| *s == sort).unwrap_or(0) as u32);
+        toolbar.append(&search_entry);
+        toolbar.append(&sort_dropdown);
+        container.append(&toolbar);
+
         let scrolled = gtk4::ScrolledWindow::builder()
             .hscrollbar_policy(gtk4::PolicyType::Never)
             .vexpand(true)
             .build();
-        let flow_box = gtk4::FlowBox::builder()
-            .selection_mode(gtk4::SelectionMode::None)
-            .valign(gtk4::Align::Start)
-            .max_children_per_line(3)
-            .min_children_per_line(1)
-            .column_spacing(16)
-            .row_spacing(16)
-            .build();
-        flow_box.add_css_class("page");
-        scrolled.set_child(Some(&flow_box));
+        let groups_box = gtk4::Box::new(gtk4::Orientation::Vertical, 16);
+        groups_box.add_css_class("page");
+        scrolled.set_child(Some(&groups_box));
+        container.append(&scrolled);
 
         let sl = Self {
-            container: scrolled,
-            flow_box,
+            container,
+            search_entry,
+            sort_dropdown,
+            groups_box,
         };
+
+        let groups = sl.groups_box.clone();
+        let sort_dd = sl.sort_dropdown.clone();
+        let oa = on_action.clone();
+        sl.search_entry.connect_search_changed(move |entry| {
+            Self::populate(&groups, &entry.text(), Self::sort_at(&sort_dd), oa.clone());
+        });
+
+        let sort_dd = sl.sort_dropdown.clone();
+        let oa = on_action.clone();
+        sl.search_entry.connect_activate(move |entry| {
+            let query = entry.text();
+            if query.trim().is_empty() {
+                return;
+            }
+            if let Some(host) = Self::visible_hosts(&query, Self::sort_at(&sort_dd))
+                .into_iter()
+                .next()
+            {
+                Self::connect(host, oa.clone());
+            }
+        });
+
+        let groups = sl.groups_box.clone();
+        let search = sl.search_entry.clone();
+        let oa = on_action.clone();
+        sl.sort_dropdown.connect_selected_notify(move |dd| {
+            let sort = Self::sort_at(dd);
+            let mut config = load_app_config().unwrap_or_default();
+            config.server_sort = sort;
+            if let Err(e) = save_app_config(&config) {
+                tracing::error!("Failed to save server sort: {}", e);
+            }
+            Self::populate(&groups, &search.text(), sort, oa.clone());
+        });
+
         sl.refresh(on_action);
         sl
     }
@@ -45,20 +113,166 @@ impl ServerList {
     where
         F: Fn(ServerAction) + 'static + Clone,
     {
-        while let Some(child) = self.flow_box.first_child() {
-            self.flow_box.remove(&child);
-        }
+        Self::populate(
+            &self.groups_box,
+            &self.search_entry.text(),
+            Self::sort_at(&self.sort_dropdown),
+            on_action,
+        );
+    }
 
-        let hosts = load_hosts().unwrap_or_else(|e| {
+    fn sort_at(dropdown: &gtk4::DropDown) -> ServerSort {
+        SORTS
+            .get(dropdown.selected() as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Hosts matching `query`, best match first. With an empty query, every host
+    /// in `sort` order.
+    fn visible_hosts(query: &str, sort: ServerSort) -> Vec<SshHost> {
+        let mut hosts = load_hosts().unwrap_or_else(|e| {
             tracing::error!("Failed to load hosts: {}", e);
             Vec::new()
         });
+        sort_hosts(&mut hosts, sort);
+        if query.trim().is_empty() {
+            return hosts;
+        }
+        let mut scored: Vec<(i32, SshHost)> = hosts
+            .into_iter()
+            .filter_map(|host| host_match_score(&host, query).map(|score| (score, host)))
+            .collect();
+        // Stable, so equal scores keep the selected sort order.
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, host)| host).collect()
+    }
+
+    fn populate<F>(groups_box: &gtk4::Box, query: &str, sort: ServerSort, on_action: F)
+    where
+        F: Fn(ServerAction) + 'static + Clone,
+    {
+        while let Some(child) = groups_box.first_child() {
+            groups_box.remove(&child);
+        }
+
+        let hosts = Self::visible_hosts(query, sort);
+
+        // Search results are a flat grid, ranked by relevance.
+        if !query.trim().is_empty() {
+            if hosts.is_empty() {
+                groups_box.append(
+                    &gtk4::Label::builder()
+                        .label("No matching hosts")
+                        .css_classes(vec!["dim-label".to_string()])
+                        .build(),
+                );
+                return;
+            }
+            let flow_box = Self::flow_box();
+            for host in &hosts {
+                Self::add_host_row(&flow_box, host, on_action.clone());
+            }
+            groups_box.append(&flow_box);
+            return;
+        }
+
+        let mut folders: BTreeMap<String, Vec<SshHost>> = BTreeMap::new();
+        let mut ungrouped = Vec::new();
         for host in hosts {
-            self.add_host_row(&host, on_action.clone());
+            match host.folder.clone() {
+                Some(folder) => folders.entry(folder).or_default().push(host),
+                None => ungrouped.push(host),
+            }
+        }
+
+        // Without folders the list stays a flat grid.
+        if folders.is_empty() {
+            let flow_box = Self::flow_box();
+            for host in &ungrouped {
+                Self::add_host_row(&flow_box, host, on_action.clone());
+            }
+            groups_box.append(&flow_box);
+            return;
+        }
+
+        let collapsed = load_app_config()
+            .map(|config| config.collapsed_folders)
+            .unwrap_or_default();
+        let ungrouped = (!ungrouped.is_empty()).then_some((String::new(), ungrouped));
+        for (folder, hosts) in folders.into_iter().chain(ungrouped) {
+            let flow_box = Self::flow_box();
+            for host in &hosts {
+                Self::add_host_row(&flow_box, host, on_action.clone());
+            }
+            let expander = Self::folder_expander(&folder, hosts.len(), &collapsed);
+            expander.set_child(Some(&flow_box));
+            groups_box.append(&expander);
         }
     }
 
-    fn add_host_row<F>(&self, host: &SshHost, on_action: F)
+    fn connect<F>(host: SshHost, on_action: F)
+    where
+        F: Fn(ServerAction) + 'static,
+    {
+        glib::MainContext::default().spawn_local(async move {
+            let password = crate::config_observer::get_keyring_password(&host.alias).await;
+            on_action(ServerAction::Connect(host, password));
+        });
+    }
+
+    fn flow_box() -> gtk4::FlowBox {
+        gtk4::FlowBox::builder()
+            .selection_mode(gtk4::SelectionMode::None)
+            .valign(gtk4::Align::Start)
+            .max_children_per_line(3)
+            .min_children_per_line(1)
+            .column_spacing(16)
+            .row_spacing(16)
+            .build()
+    }
+
+    /// A collapsible folder header; `folder` is empty for hosts without one.
+    /// The collapsed state is remembered in the app config.
+    fn folder_expander(folder: &str, count: usize, collapsed: &[String]) -> gtk4::Expander {
+        let header = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+        header.append(
+            &gtk4::Label::builder()
+                .label(if folder.is_empty() {
+                    "Ungrouped"
+                } else {
+                    folder
+                })
+                .css_classes(vec!["title-4".to_string()])
+                .build(),
+        );
+        header.append(
+            &gtk4::Label::builder()
+                .label(count.to_string())
+                .css_classes(vec!["dim-label".to_string(), "caption".to_string()])
+                .build(),
+        );
+        let expander = gtk4::Expander::builder()
+            .label_widget(&header)
+            .expanded(!collapsed.iter().any(|f| f == folder))
+            .build();
+        expander.add_css_class("server-folder");
+
+        let folder = folder.to_string();
+        expander.connect_expanded_notify(move |expander| {
+            let mut config = load_app_config().unwrap_or_default();
+            config.collapsed_folders.retain(|f| *f != folder);
+            if !expander.is_expanded() {
+                config.collapsed_folders.push(folder.clone());
+            }
+            if let Err(e) = save_app_config(&config) {
+                tracing::error!("Failed to save collapsed folders: {}", e);
+            }
+        });
+        expander
+    }
+
+    fn add_host_row<F>(flow_box: &gtk4::FlowBox, host: &SshHost, on_action: F)
     where
         F: Fn(ServerAction) + 'static + Clone,
     {
@@ -97,6 +311,15 @@ impl ServerList {
 
         let actions_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 2);
         actions_box.add_css_class("server-card-actions");
+        let test_btn = gtk4::Button::from_icon_name("network-transmit-receive-symbolic");
+        test_btn.add_css_class("flat");
+        test_btn.set_tooltip_text(Some("Test Connection"));
+        let host_test = host.clone();
+        let on_action_test = on_action.clone();
+        test_btn.connect_clicked(move |_| {
+            on_action_test(ServerAction::Test(host_test.clone()));
+        });
+
         let edit_btn = gtk4::Button::from_icon_name("document-edit-symbolic");
         edit_btn.add_css_class("flat");
         let host_edit = host.clone();
@@ -114,6 +337,15 @@ impl ServerList {
             on_action_del(ServerAction::Delete(host_del.clone()));
         });
 
+        actions_box.append(&test_btn);
+        if let Some(inventory) = &host.inventory {
+            let tooltip = format!("Managed by the Ansible inventory {}", inventory);
+            for btn in [&edit_btn, &delete_btn] {
+                btn.set_sensitive(false);
+                btn.set_tooltip_text(Some(&tooltip));
+            }
+        }
+
         actions_box.append(&edit_btn);
         actions_box.append(&delete_btn);
 
@@ -122,20 +354,204 @@ impl ServerList {
         header_box.append(&actions_box);
         content_box.append(&header_box);
 
+        if !host.tags.is_empty() {
+            let tags_box = gtk4::FlowBox::builder()
+                .selection_mode(gtk4::SelectionMode::None)
+                .max_children_per_line(6)
+                .column_spacing(4)
+                .row_spacing(4)
+                .build();
+            for tag in &host.tags {
+                let label = gtk4::Label::new(Some(tag));
+                label.add_css_class("host-tag");
+                label.add_css_class(&format!("tag-color-{}", tag_color_index(tag)));
+                tags_box.insert(&label, -1);
+            }
+            content_box.append(&tags_box);
+        }
+
         let gesture = gtk4::GestureClick::new();
         let host_conn = host.clone();
         let on_action_conn = on_action.clone();
         gesture.connect_released(move |_, _, _, _| {
-            let h = host_conn.clone();
-            let oa = on_action_conn.clone();
-            glib::MainContext::default().spawn_local(async move {
-                let password = crate::config_observer::get_keyring_password(&h.alias).await;
-                oa(ServerAction::Connect(h, password));
-            });
+            Self::connect(host_conn.clone(), on_action_conn.clone());
         });
         frame.add_controller(gesture);
 
         frame.set_child(Some(&content_box));
-        self.flow_box.insert(&frame, -1);
+        flow_box.insert(&frame, -1);
+    }
+}
+
+/// Picks a stable colour for a tag, so the same tag looks the same on every card.
+fn tag_color_index(tag: &str) -> u32 {
+    tag.to_lowercase()
+        .bytes()
+        .fold(0u32, |hash, b| hash.wrapping_mul(31).wrapping_add(b as u32))
+        % TAG_COLORS
+}
+
+fn sort_hosts(hosts: &mut [SshHost], sort: ServerSort) {
+    let usage = match sort {
+        ServerSort::LastConnected | ServerSort::MostUsed => load_host_usage().unwrap_or_else(|e| {
+            tracing::warn!("Failed to load host usage: {}", e);
+            Default::default()
+        }),
+        _ => Default::default(),
+    };
+    sort_hosts_by(hosts, sort, &usage);
+}
+
+fn sort_hosts_by(hosts: &mut [SshHost], sort: ServerSort, usage: &BTreeMap<String, HostUsage>) {
+    let usage_of = |host: &SshHost| usage.get(&host.alias).cloned().unwrap_or_default();
+    match sort {
+        ServerSort::ConfigOrder => {}
+        ServerSort::Name => hosts.sort_by_key(|h| h.alias.to_lowercase()),
+        ServerSort::LastConnected => {
+            hosts.sort_by_key(|h| std::cmp::Reverse(usage_of(h).last_connected))
+        }
+        ServerSort::MostUsed => hosts.sort_by_key(|h| {
+            let usage = usage_of(h);
+            std::cmp::Reverse((usage.connections, usage.last_connected))
+        }),
+    }
+}
+
+/// Fuzzy score of `host` for a whitespace-separated query: each term has to
+/// match the alias, hostname, user or a tag. Alias matches rank highest.
+fn host_match_score(host: &SshHost, query: &str) -> Option<i32> {
+    query.split_whitespace().try_fold(0, |total, term| {
+        let fields = [
+            host.hostname.as_str(),
+            host.user.as_deref().unwrap_or_default(),
+        ];
+        let best = fields
+            .into_iter()
+            .chain(host.tags.iter().map(String::as_str))
+            .filter_map(|field| fuzzy_score(term, field))
+            .chain(fuzzy_score(term, &host.alias).map(|score| score * 2))
+            .max()?;
+        Some(total + best)
+    })
+}
+
+/// Scores `text` as a fuzzy match for `query`: every query character must
+/// appear in order (case-insensitively). Substrings, consecutive characters and
+/// matches at word starts score higher.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let query = query.to_lowercase();
+    let text = text.to_lowercase();
+    let chars: Vec<char> = text.chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for qc in query.chars() {
+        let index = (next..chars.len()).find(|&i| chars[i] == qc)?;
+        score += 1;
+        if index > 0 && previous == Some(index - 1) {
+            score += 4;
+        }
+        if index == 0 || !chars[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(index);
+        next = index + 1;
+    }
+    if text == query {
+        score += 20;
+    } else if text.starts_with(&query) {
+        score += 10;
+    } else if text.contains(&query) {
+        score += 5;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(alias: &str, hostname: &str, tags: &[&str]) -> SshHost {
+        SshHost {
+            alias: alias.to_string(),
+            hostname: hostname.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_prefixes_over_scattered_matches() {
+        let prefix = fuzzy_score("web", "webserver").unwrap();
+        let scattered = fuzzy_score("web", "wide-area-backup").unwrap();
+        assert!(prefix > scattered);
+        assert!(fuzzy_score("web", "web").unwrap() > prefix);
+        assert_eq!(fuzzy_score("bew", "web"), None);
+    }
+
+    #[test]
+    fn test_host_match_score_ranks_alias_above_hostname_and_tags() {
+        let by_alias = host("prod", "10.0.0.1", &[]);
+        let by_hostname = host("alpha", "prod.example.com", &[]);
+        let by_tag = host("beta", "10.0.0.2", &["prod"]);
+        let alias_score = host_match_score(&by_alias, "prod").unwrap();
+        assert!(alias_score > host_match_score(&by_hostname, "prod").unwrap());
+        assert!(alias_score > host_match_score(&by_tag, "prod").unwrap());
+    }
+
+    #[test]
+    fn test_host_match_score_requires_every_term() {
+        let web = host("web1", "10.0.0.1", &["eu"]);
+        assert!(host_match_score(&web, "web eu").is_some());
+        assert!(host_match_score(&web, "web us").is_none());
+        assert_eq!(host_match_score(&web, ""), Some(0));
+    }
+
+    #[test]
+    fn test_sort_hosts_by_each_mode() {
+        let hosts = vec![
+            host("delta", "d", &[]),
+            host("Alpha", "a", &[]),
+            host("charlie", "c", &[]),
+        ];
+        let usage = BTreeMap::from([
+            (
+                "delta".to_string(),
+                HostUsage {
+                    last_connected: Some(100),
+                    connections: 5,
+                },
+            ),
+            (
+                "charlie".to_string(),
+                HostUsage {
+                    last_connected: Some(200),
+                    connections: 5,
+                },
+            ),
+            (
+                "Alpha".to_string(),
+                HostUsage {
+                    last_connected: Some(300),
+                    connections: 1,
+                },
+            ),
+        ]);
+        let sorted = |sort| {
+            let mut hosts = hosts.clone();
+            sort_hosts_by(&mut hosts, sort, &usage);
+            hosts.into_iter().map(|h| h.alias).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sorted(ServerSort::ConfigOrder),
+            ["delta", "Alpha", "charlie"]
+        );
+        assert_eq!(sorted(ServerSort::Name), ["Alpha", "charlie", "delta"]);
+        assert_eq!(
+            sorted(ServerSort::LastConnected),
+            ["Alpha", "charlie", "delta"]
+        );
+        // Ties on the connection count go to the most recent.
+        assert_eq!(sorted(ServerSort::MostUsed), ["charlie", "delta", "Alpha"]);
     }
 }
@@ -1,5 +1,7 @@
 #![allow(deprecated)]
-use crate::config_observer::{REMOTE_SSH_DIR, SshKeyPair, get_ssh_dir, load_hosts, load_ssh_keys};
+use crate::config_observer::{
+    REMOTE_SSH_DIR, SshKeyPair, get_ssh_dir, load_certificate, load_hosts, load_ssh_keys,
+};
 use gtk4::prelude::*;
 use gtk4::{gio, glib};
 use std::cell::RefCell;
@@ -29,6 +31,50 @@ fn show_error_alert(parent: Option<&gtk4::Window>, title: &str, secondary: &str)
     dialog.show(parent);
 }
 
+/// One-line summary of a key's certificate: principals and expiry, in warning
+/// colours when it is about to expire or already has.
+fn certificate_label(cert_path: &std::path::Path) -> gtk4::Label {
+    let label = gtk4::Label::builder()
+        .halign(gtk4::Align::Start)
+        .wrap(true)
+        .css_classes(vec!["caption".to_string()])
+        .build();
+    let cert = match load_certificate(cert_path) {
+        Ok(cert) => cert,
+        Err(e) => {
+            label.set_label(&format!("Certificate: {:#}", e));
+            label.add_css_class("error");
+            return label;
+        }
+    };
+    let principals = if cert.principals.is_empty() {
+        "any principal".to_string()
+    } else {
+        cert.principals.join(", ")
+    };
+    let expiry = match cert.expires_at() {
+        None => "never expires".to_string(),
+        Some(at) => {
+            let local = at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+            if cert.is_expired() {
+                format!("expired {}", local)
+            } else {
+                format!("expires {}", local)
+            }
+        }
+    };
+    label.set_label(&format!("Certificate for {} · {}", principals, expiry));
+    label.set_tooltip_text(Some(&format!("Key ID: {}", cert.key_id)));
+    if cert.is_expired() {
+        label.add_css_class("error");
+    } else if cert.expires_soon() {
+        label.add_css_class("warning");
+    } else {
+        label.add_css_class("dim-label");
+    }
+    label
+}
+
 pub fn build_ssh_keys_ui(window: &gtk4::ApplicationWindow) -> gtk4::Box {
     let main_box = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
     main_box.add_css_class("page");
@@ -112,9 +158,14 @@ pub fn build_ssh_keys_ui(window: &gtk4::ApplicationWindow) -> gtk4::Box {
                     let icon = gtk4::Image::from_icon_name("network-vpn-symbolic");
                     icon.set_pixel_size(20);
                     icon.set_opacity(0.7);
+                    let name_box = gtk4::Box::new(gtk4::Orientation::Vertical, 2);
+                    name_box.set_hexpand(true);
                     let name_lbl = gtk4::Label::new(Some(&key.name));
                     name_lbl.set_halign(gtk4::Align::Start);
-                    name_lbl.set_hexpand(true);
+                    name_box.append(&name_lbl);
+                    if let Some(ref cert_path) = key.certificate {
+                        name_box.append(&certificate_label(cert_path));
+                    }
 
                     let deploy_btn = gtk4::Button::from_icon_name("document-send-symbolic");
                     deploy_btn.set_tooltip_text(Some("Deploy to Server"));
@@ -168,7 +219,7 @@ pub fn build_ssh_keys_ui(window: &gtk4::ApplicationWindow) -> gtk4::Box {
                     });
 
                     hbox.append(&icon);
-                    hbox.append(&name_lbl);
+                    hbox.append(&name_box);
                     hbox.append(&deploy_btn);
                     hbox.append(&del_btn);
                     row.set_child(Some(&hbox));
@@ -385,6 +385,24 @@ pub fn init_style() {
         .server-card-actions button:hover {
             opacity: 1;
         }
+        .server-list-toolbar {
+            margin: 24px 40px 0 40px;
+        }
+        .server-folder > title {
+            padding: 4px 0 8px 0;
+        }
+        .host-tag {
+            font-size: 0.75em;
+            font-weight: 600;
+            padding: 1px 8px;
+            border-radius: 999px;
+        }
+        .tag-color-0 { background-color: alpha(#3584e4, 0.2); color: #3584e4; }
+        .tag-color-1 { background-color: alpha(#2ec27e, 0.2); color: #2ec27e; }
+        .tag-color-2 { background-color: alpha(#e5a50a, 0.2); color: #e5a50a; }
+        .tag-color-3 { background-color: alpha(#e01b24, 0.2); color: #e01b24; }
+        .tag-color-4 { background-color: alpha(#9141ac, 0.2); color: #9141ac; }
+        .tag-color-5 { background-color: alpha(#ff7800, 0.2); color: #ff7800; }
 
         /* ── Dialog ────────────────────────────────────────── */
         dialog .dialog-content {
@@ -0,0 +1,354 @@
+use crate::config_observer::SshHost;
+use crate::engines::tunnel::{
+    Tunnel, TunnelKind, list_tunnels, start_dynamic_forward, start_local_forward,
+    start_remote_forward,
+};
+use crate::ui::file_explorer::format_file_size;
+use gtk4::glib;
+use gtk4::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub struct TunnelManager {
+    pub container: gtk4::Box,
+}
+
+impl TunnelManager {
+    pub fn new(host: SshHost, password: Option<String>) -> Self {
+        let container = gtk4::Box::new(gtk4::Orientation::Vertical, 24);
+        container.add_css_class("page");
+
+        let header_box = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+        header_box.add_css_class("page-header");
+        let title = gtk4::Label::builder()
+            .label("Port Forwarding")
+            .halign(gtk4::Align::Start)
+            .css_classes(vec!["title-1".to_string()])
+            .build();
+        let subtitle = gtk4::Label::builder()
+            .label(format!("Server: {}", host.alias))
+            .halign(gtk4::Align::Start)
+            .css_classes(vec!["page-subtitle".to_string()])
+            .build();
+        header_box.append(&title);
+        header_box.append(&subtitle);
+        container.append(&header_box);
+
+        // Local (-L): listen here, connect from the server. Remote (-R): the reverse.
+        // Dynamic (-D): listen here, the SOCKS client picks the target.
+        let form = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+        let direction_dropdown =
+            gtk4::DropDown::from_strings(&["Local (-L)", "Remote (-R)", "Dynamic (-D)"]);
+        let listen_port_entry = gtk4::Entry::builder()
+            .placeholder_text("Local port")
+            .width_chars(10)
+            .build();
+        let target_host_entry = gtk4::Entry::builder()
+            .placeholder_text("Remote host")
+            .text("localhost")
+            .hexpand(true)
+            .build();
+        let target_port_entry = gtk4::Entry::builder()
+            .placeholder_text("Remote port")
+            .width_chars(10)
+            .build();
+        let start_btn = gtk4::Button::with_label("Start");
+        start_btn.add_css_class("suggested-action");
+        form.append(&direction_dropdown);
+        form.append(&listen_port_entry);
+        let arrow = gtk4::Label::new(Some("→"));
+        form.append(&arrow);
+        form.append(&target_host_entry);
+        form.append(&target_port_entry);
+        form.append(&start_btn);
+        container.append(&form);
+
+        let lp = listen_port_entry.clone();
+        let th = target_host_entry.clone();
+        let tp = target_port_entry.clone();
+        direction_dropdown.connect_selected_notify(move |dd| {
+            let (listen, target) = if dd.selected() == 1 {
+                ("Remote port", "Local")
+            } else {
+                ("Local port", "Remote")
+            };
+            lp.set_placeholder_text(Some(listen));
+            th.set_placeholder_text(Some(&format!("{} host", target)));
+            tp.set_placeholder_text(Some(&format!("{} port", target)));
+            let has_target = dd.selected() != 2;
+            th.set_visible(has_target);
+            tp.set_visible(has_target);
+            arrow.set_visible(has_target);
+        });
+
+        let error_label = gtk4::Label::builder()
+            .halign(gtk4::Align::Start)
+            .visible(false)
+            .wrap(true)
+            .build();
+        error_label.add_css_class("error");
+        container.append(&error_label);
+
+        let scrolled = gtk4::ScrolledWindow::builder().vexpand(true).build();
+        let list_box = gtk4::ListBox::new();
+        list_box.set_selection_mode(gtk4::SelectionMode::None);
+        list_box.add_css_class("boxed-list");
+        list_box.set_valign(gtk4::Align::Start);
+        let placeholder = gtk4::Label::builder()
+            .label("No active tunnels")
+            .css_classes(vec!["dim-label".to_string()])
+            .margin_top(12)
+            .margin_bottom(12)
+            .build();
+        list_box.set_placeholder(Some(&placeholder));
+        scrolled.set_child(Some(&list_box));
+        container.append(&scrolled);
+
+        // Tunnels outlive the tab, so show the ones already running for this host.
+        for tunnel in list_tunnels(&host.alias) {
+            Self::append_tunnel_row(&list_box, tunnel);
+        }
+
+        let lb = list_box.clone();
+        start_btn.connect_clicked(move |btn| {
+            error_label.set_visible(false);
+            let mode = direction_dropdown.selected();
+            // A SOCKS proxy has no fixed target; any valid placeholder port will do.
+            let parsed = (
+                listen_port_entry.text().trim().parse::<u16>(),
+                match mode {
+                    2 => Ok(1),
+                    _ => target_port_entry.text().trim().parse::<u16>(),
+                },
+            );
+            let (Ok(listen_port @ 1..), Ok(target_port @ 1..)) = parsed else {
+                error_label.set_label("Ports must be numbers between 1 and 65535");
+                error_label.set_visible(true);
+                return;
+            };
+            let target_host = match target_host_entry.text().trim() {
+                "" => "localhost".to_string(),
+                h => h.to_string(),
+            };
+
+            let h = host.clone();
+            let p = password.clone();
+            let lb = lb.clone();
+            let btn = btn.clone();
+            let error_label = error_label.clone();
+            btn.set_sensitive(false);
+            glib::MainContext::default().spawn_local(async move {
+                let result = match mode {
+                    1 => {
+                        start_remote_forward(
+                            &h,
+                            p.as_deref(),
+                            listen_port,
+                            &target_host,
+                            target_port,
+                        )
+                        .await
+                    }
+                    2 => start_dynamic_forward(&h, p.as_deref(), listen_port).await,
+                    _ => {
+                        start_local_forward(
+                            &h,
+                            p.as_deref(),
+                            listen_port,
+                            &target_host,
+                            target_port,
+                        )
+                        .await
+                    }
+                };
+                match result {
+                    Ok(tunnel) => Self::append_tunnel_row(&lb, tunnel),
+                    Err(e) => {
+                        tracing::error!("Failed to start tunnel on {}: {:#}", h.alias, e);
+                        error_label.set_label(&format!("{:#}", e));
+                        error_label.set_visible(true);
+                    }
+                }
+                btn.set_sensitive(true);
+            });
+        });
+
+        Self { container }
+    }
+
+    fn append_tunnel_row(list_box: &gtk4::ListBox, tunnel: Arc<Tunnel>) {
+        let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 12);
+        row.add_css_class("list-row-content");
+
+        let text_box = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+        let name_label = gtk4::Label::builder()
+            .label(tunnel.kind.describe())
+            .halign(gtk4::Align::Start)
+            .css_classes(vec!["bold".to_string()])
+            .build();
+        let stats_label = gtk4::Label::builder()
+            .label(Self::format_stats(&tunnel))
+            .halign(gtk4::Align::Start)
+            .css_classes(vec!["caption".to_string(), "dim-label".to_string()])
+            .build();
+        text_box.append(&name_label);
+        text_box.append(&stats_label);
+        row.append(&text_box);
+
+        let spacer = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+        spacer.set_hexpand(true);
+        row.append(&spacer);
+
+        let stop_btn = gtk4::Button::from_icon_name("media-playback-stop-symbolic");
+        stop_btn.add_css_class("flat");
+        stop_btn.set_tooltip_text(Some("Stop Tunnel"));
+        stop_btn.set_valign(gtk4::Align::Center);
+        row.append(&stop_btn);
+
+        list_box.append(&row);
+
+        let lb = list_box.clone();
+        let row_stop = row.clone();
+        let tunnel_stop = tunnel.clone();
+        stop_btn.connect_clicked(move |_| {
+            tunnel_stop.stop();
+            if let Some(list_row) = row_stop.parent() {
+                lb.remove(&list_row);
+            }
+        });
+
+        let weak_label = stats_label.downgrade();
+        glib::timeout_add_local(Duration::from_secs(1), move || {
+            let Some(label) = weak_label.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            if tunnel.is_stopped() {
+                return glib::ControlFlow::Break;
+            }
+            label.set_label(&Self::format_stats(&tunnel));
+            glib::ControlFlow::Continue
+        });
+    }
+
+    fn format_stats(tunnel: &Tunnel) -> String {
+        let connections = tunnel.connections();
+        format!(
+            "↑ {}  ↓ {} · {} connection{}",
+            format_file_size(tunnel.bytes_sent()),
+            format_file_size(tunnel.bytes_received()),
+            connections,
+            if connections == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Session toolbar button that starts and stops a SOCKS5 proxy (`ssh -D`) for the host.
+pub fn build_socks_button(host: SshHost, password: Option<String>) -> gtk4::MenuButton {
+    let button = gtk4::MenuButton::builder()
+        .icon_name("network-vpn-symbolic")
+        .tooltip_text("SOCKS5 Proxy")
+        .build();
+    button.add_css_class("flat");
+
+    let content = gtk4::Box::new(gtk4::Orientation::Vertical, 8);
+    content.set_margin_top(8);
+    content.set_margin_bottom(8);
+    content.set_margin_start(8);
+    content.set_margin_end(8);
+    let title = gtk4::Label::builder()
+        .label("SOCKS5 Proxy")
+        .halign(gtk4::Align::Start)
+        .css_classes(vec!["bold".to_string()])
+        .build();
+    let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+    let port_entry = gtk4::Entry::builder()
+        .placeholder_text("Local port")
+        .text("1080")
+        .width_chars(8)
+        .build();
+    let toggle_btn = gtk4::Button::with_label("Start");
+    row.append(&port_entry);
+    row.append(&toggle_btn);
+    let status_label = gtk4::Label::builder()
+        .halign(gtk4::Align::Start)
+        .wrap(true)
+        .max_width_chars(32)
+        .css_classes(vec!["caption".to_string(), "dim-label".to_string()])
+        .build();
+    content.append(&title);
+    content.append(&row);
+    content.append(&status_label);
+
+    let popover = gtk4::Popover::new();
+    popover.set_child(Some(&content));
+    button.set_popover(Some(&popover));
+
+    let running: Rc<RefCell<Option<Arc<Tunnel>>>> = Rc::new(RefCell::new(None));
+    let sync_state = {
+        let running = running.clone();
+        let alias = host.alias.clone();
+        let port_entry = port_entry.clone();
+        let toggle_btn = toggle_btn.clone();
+        let status_label = status_label.clone();
+        move || {
+            // The proxy may also have been started or stopped from the Tunnels tab.
+            let current = list_tunnels(&alias)
+                .into_iter()
+                .find(|t| matches!(t.kind, TunnelKind::Dynamic { .. }));
+            match &current {
+                Some(tunnel) => {
+                    toggle_btn.set_label("Stop");
+                    toggle_btn.remove_css_class("suggested-action");
+                    toggle_btn.add_css_class("destructive-action");
+                    port_entry.set_sensitive(false);
+                    status_label.set_label(&tunnel.kind.describe());
+                }
+                None => {
+                    toggle_btn.set_label("Start");
+                    toggle_btn.remove_css_class("destructive-action");
+                    toggle_btn.add_css_class("suggested-action");
+                    port_entry.set_sensitive(true);
+                    status_label.set_label("Not running");
+                }
+            }
+            *running.borrow_mut() = current;
+        }
+    };
+    sync_state();
+    let sync_state = Rc::new(sync_state);
+
+    let sync_show = sync_state.clone();
+    popover.connect_show(move |_| sync_show());
+
+    toggle_btn.connect_clicked(move |btn| {
+        if let Some(tunnel) = running.borrow_mut().take() {
+            tunnel.stop();
+            sync_state();
+            return;
+        }
+        let Ok(port @ 1..) = port_entry.text().trim().parse::<u16>() else {
+            status_label.set_label("Port must be a number between 1 and 65535");
+            return;
+        };
+        let h = host.clone();
+        let p = password.clone();
+        let btn = btn.clone();
+        let status_label = status_label.clone();
+        let sync_state = sync_state.clone();
+        btn.set_sensitive(false);
+        glib::MainContext::default().spawn_local(async move {
+            match start_dynamic_forward(&h, p.as_deref(), port).await {
+                Ok(_) => sync_state(),
+                Err(e) => {
+                    tracing::error!("Failed to start SOCKS5 proxy on {}: {:#}", h.alias, e);
+                    status_label.set_label(&format!("{:#}", e));
+                }
+            }
+            btn.set_sensitive(true);
+        });
+    });
+
+    button
+}
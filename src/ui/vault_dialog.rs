@@ -0,0 +1,118 @@
+#![allow(deprecated)]
+use crate::config_observer::PasswordStorage;
+use crate::credentials::{active_storage, is_vault_unlocked, unlock_vault, vault_exists};
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+
+/// Asks for the master password when secrets go to the vault and it is
+/// still locked for this session.
+pub fn unlock_vault_if_needed(parent: &gtk4::Window) {
+    let parent = parent.clone();
+    glib::MainContext::default().spawn_local(async move {
+        if active_storage().await == PasswordStorage::Vault && !is_vault_unlocked() {
+            show_unlock_dialog(&parent);
+        }
+    });
+}
+
+fn show_unlock_dialog(parent: &gtk4::Window) {
+    let creating = !vault_exists();
+    let dialog = gtk4::Dialog::builder()
+        .transient_for(parent)
+        .modal(true)
+        .title(if creating {
+            "Create Password Vault"
+        } else {
+            "Unlock Password Vault"
+        })
+        .default_width(400)
+        .build();
+
+    let content = dialog.content_area();
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    content.set_spacing(12);
+
+    content.append(
+        &gtk4::Label::builder()
+            .label(if creating {
+                "Saved passwords will be kept in a file encrypted with a master password. \
+                 It can't be recovered if you forget it."
+            } else {
+                "Enter the master password to use saved passwords in this session."
+            })
+            .halign(gtk4::Align::Start)
+            .wrap(true)
+            .build(),
+    );
+
+    let master = gtk4::PasswordEntry::builder()
+        .show_peek_icon(true)
+        .activates_default(true)
+        .build();
+    content.append(&master);
+    let confirm = gtk4::PasswordEntry::builder()
+        .show_peek_icon(true)
+        .activates_default(true)
+        .placeholder_text("Confirm master password")
+        .visible(creating)
+        .build();
+    content.append(&confirm);
+
+    let error_label = gtk4::Label::builder()
+        .halign(gtk4::Align::Start)
+        .wrap(true)
+        .css_classes(vec!["error".to_string()])
+        .visible(false)
+        .build();
+    content.append(&error_label);
+
+    let unlock_button = dialog.add_button(
+        if creating { "Create" } else { "Unlock" },
+        gtk4::ResponseType::Ok,
+    );
+    dialog.add_button("Not Now", gtk4::ResponseType::Cancel);
+    dialog.set_default_widget(Some(&unlock_button));
+
+    dialog.connect_response(move |d, res| {
+        if res != gtk4::ResponseType::Ok {
+            d.close();
+            return;
+        }
+        let password = zeroize::Zeroizing::new(master.text().to_string());
+        let problem = if password.is_empty() {
+            Some("Enter a master password.")
+        } else if creating && confirm.text().as_str() != password.as_str() {
+            Some("The passwords don't match.")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            error_label.set_text(problem);
+            error_label.set_visible(true);
+            return;
+        }
+
+        d.set_response_sensitive(gtk4::ResponseType::Ok, false);
+        let (d, master, error_label) = (d.clone(), master.clone(), error_label.clone());
+        glib::MainContext::default().spawn_local(async move {
+            let result = gio::spawn_blocking(move || unlock_vault(&password))
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Unlock task failed")));
+            match result {
+                Ok(()) => d.close(),
+                Err(e) => {
+                    d.set_response_sensitive(gtk4::ResponseType::Ok, true);
+                    error_label.set_text(&format!("{:#}", e));
+                    error_label.set_visible(true);
+                    master.set_text("");
+                    master.grab_focus();
+                }
+            }
+        });
+    });
+
+    dialog.present();
+}
@@ -1,14 +1,22 @@
-use crate::config_observer::{SshHost, add_host_to_config, delete_host_from_config, load_hosts};
+use crate::config_observer::{
+    SshHost, add_host_to_config, add_hosts_to_config, delete_host_from_config, load_hosts,
+    update_host_in_config,
+};
 use crate::ui::add_server_dialog::show_server_dialog;
+use crate::ui::app_lock::{AppLock, show_set_password_dialog};
 use crate::ui::components::header::Header;
 use crate::ui::components::settings::Settings;
 use crate::ui::components::sidebar::Sidebar;
+use crate::ui::config_watcher::ConfigWatcher;
+use crate::ui::diagnostics_dialog::show_diagnostics_dialog;
 use crate::ui::docker::DockerManager;
 use crate::ui::file_explorer::FileExplorer;
+use crate::ui::import_dialog::show_import_dialog;
 use crate::ui::monitor::SystemMonitor;
 use crate::ui::server_list::{ServerAction, ServerList};
 use crate::ui::ssh_keys::build_ssh_keys_ui;
 use crate::ui::style::init_style;
+use crate::ui::tunnels::{TunnelManager, build_socks_button};
 use gtk4::prelude::*;
 use gtk4::{gdk, gio, glib};
 use std::cell::RefCell;
@@ -25,6 +33,9 @@ struct AppWindowInner {
     stack: gtk4::Stack,
     notebook: gtk4::Notebook,
     sidebar: Sidebar,
+    settings: Settings,
+    app_lock: AppLock,
+    config_watcher: RefCell<Option<ConfigWatcher>>,
 }
 
 impl AppWindow {
@@ -71,7 +82,15 @@ impl AppWindow {
         root.append(&sidebar.container);
         root.append(&separator);
         root.append(&content_box);
-        window.set_child(Some(&root));
+        let app_lock = AppLock::new(
+            &window,
+            &root,
+            vec![
+                header.add_btn.clone().upcast(),
+                header.import_btn.clone().upcast(),
+                header.lock_btn.clone().upcast(),
+            ],
+        );
 
         let app_window = Self {
             inner: Rc::new(AppWindowInner {
@@ -79,12 +98,20 @@ impl AppWindow {
                 stack,
                 notebook,
                 sidebar: sidebar.clone(),
+                settings,
+                app_lock,
+                config_watcher: RefCell::new(None),
             }),
         };
 
         app_window.setup_callbacks(sidebar, header);
+        app_window.watch_config();
         app_window.refresh();
         app_window.inner.window.present();
+        // Otherwise the vault is opened once the app lock is.
+        if !crate::credentials::is_app_locked() {
+            crate::ui::vault_dialog::unlock_vault_if_needed(app_window.inner.window.upcast_ref());
+        }
         app_window
     }
 
@@ -112,6 +139,28 @@ impl AppWindow {
             this.show_add_server_dialog();
         });
 
+        let this = self.clone();
+        header.import_btn.connect_clicked(move |_| {
+            this.show_import_dialog();
+        });
+
+        let this = self.clone();
+        header.lock_btn.connect_clicked(move |_| {
+            if this.inner.app_lock.lock() {
+                return;
+            }
+            let window = this.inner.window.clone();
+            let this = this.clone();
+            show_set_password_dialog(window.upcast_ref(), move |enabled| {
+                if enabled {
+                    if let Ok(config) = crate::config_observer::load_app_config() {
+                        this.inner.settings.apply_config(&config);
+                    }
+                    this.inner.app_lock.lock();
+                }
+            });
+        });
+
         self.inner
             .notebook
             .connect_page_reordered(|nb, child, page_num| {
@@ -121,6 +170,25 @@ impl AppWindow {
             });
     }
 
+    /// Refreshes the server list and settings when the configs change on disk.
+    fn watch_config(&self) {
+        let hosts_window = Rc::downgrade(&self.inner);
+        let settings_window = Rc::downgrade(&self.inner);
+        let watcher = ConfigWatcher::new(
+            move || {
+                if let Some(inner) = hosts_window.upgrade() {
+                    Self { inner }.refresh();
+                }
+            },
+            move |config| {
+                if let Some(inner) = settings_window.upgrade() {
+                    inner.settings.apply_config(config);
+                }
+            },
+        );
+        self.inner.config_watcher.replace(Some(watcher));
+    }
+
     fn show_sessions(&self) {
         let mut sl_idx = None;
         for i in 0..self.inner.notebook.n_pages() {
@@ -173,12 +241,58 @@ impl AppWindow {
         );
     }
 
+    fn show_import_dialog(&self) {
+        let this = self.clone();
+        let existing_aliases: Vec<String> = load_hosts()
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to load hosts: {}", e);
+                Vec::new()
+            })
+            .into_iter()
+            .map(|h| h.alias.to_lowercase())
+            .collect();
+
+        show_import_dialog(
+            self.inner.window.upcast_ref(),
+            existing_aliases,
+            move |imported| {
+                let hosts: Vec<SshHost> = imported.iter().map(|i| i.host.clone()).collect();
+                if let Err(e) = add_hosts_to_config(&hosts) {
+                    tracing::error!("Failed to import hosts: {}", e);
+                    return;
+                }
+                tracing::info!("Imported {} host(s)", hosts.len());
+                let passwords: Vec<(String, zeroize::Zeroizing<String>)> = imported
+                    .into_iter()
+                    .filter_map(|i| {
+                        i.password
+                            .map(|p| (i.host.alias, zeroize::Zeroizing::new(p)))
+                    })
+                    .collect();
+                if !passwords.is_empty() {
+                    glib::MainContext::default().spawn_local(async move {
+                        for (alias, password) in passwords {
+                            if let Err(e) =
+                                crate::config_observer::store_keyring_password(&alias, &password)
+                                    .await
+                            {
+                                tracing::error!("Failed to store password for {}: {}", alias, e);
+                            }
+                        }
+                    });
+                }
+                this.refresh();
+            },
+        );
+    }
+
     pub fn refresh(&self) {
         let this = self.clone();
         let sl = ServerList::new(move |action| match action {
             ServerAction::Connect(host, password) => this.connect_to_server(host, password),
             ServerAction::Delete(host) => this.delete_server(host),
             ServerAction::Edit(host) => this.edit_server(host),
+            ServerAction::Test(host) => this.test_server(host),
         });
 
         let mut server_list_idx = None;
@@ -211,6 +325,9 @@ impl AppWindow {
     }
 
     fn connect_to_server(&self, host: SshHost, password: Option<String>) {
+        if let Err(e) = crate::config_observer::record_host_connection(&host.alias) {
+            tracing::warn!("Failed to record connection to {}: {}", host.alias, e);
+        }
         self.inner.stack.set_visible_child_name("sessions");
         let session_box = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
         let toolbar = gtk4::Box::new(gtk4::Orientation::Horizontal, 4);
@@ -229,9 +346,15 @@ impl AppWindow {
         docker_btn.add_css_class("flat");
         docker_btn.set_tooltip_text(Some("Docker Management"));
 
+        let tunnels_btn = gtk4::Button::from_icon_name("network-wired-symbolic");
+        tunnels_btn.add_css_class("flat");
+        tunnels_btn.set_tooltip_text(Some("Port Forwarding"));
+
         toolbar.append(&explorer_btn);
         toolbar.append(&monitor_btn);
         toolbar.append(&docker_btn);
+        toolbar.append(&tunnels_btn);
+        toolbar.append(&build_socks_button(host.clone(), password.clone()));
         session_box.append(&toolbar);
 
         let terminal = vte4::Terminal::new();
@@ -352,16 +475,110 @@ impl AppWindow {
             Self::spawn_docker(&notebook_docker, host_docker.clone(), pass_docker.clone());
         });
 
-        self.spawn_ssh_process(&terminal, &host);
+        let notebook_tunnels = self.inner.notebook.clone();
+        let host_tunnels = host.clone();
+        let pass_tunnels = password.clone();
+        tunnels_btn.connect_clicked(move |_| {
+            Self::spawn_tunnels(
+                &notebook_tunnels,
+                host_tunnels.clone(),
+                pass_tunnels.clone(),
+            );
+        });
+
+        // libssh2 can't service the channels a forwarded agent or X11 display
+        // opens back to us, so those hosts get the system `ssh` instead.
+        let forwards = host.forward_agent || host.forward_x11;
+        if app_config.embedded_terminal && !forwards {
+            self.spawn_embedded_shell(&terminal, &session_box, host, password);
+            return;
+        }
+        if app_config.embedded_terminal {
+            tracing::info!(
+                "{} forwards the agent or X11, using the system ssh terminal",
+                host.alias
+            );
+        }
+
+        // Verify the host key through the engine first so the terminal obeys the
+        // same known_hosts rules (and the same prompt) as the other tabs.
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            match crate::engines::ssh::verify_host(&host).await {
+                Ok(()) => {
+                    let passphrase_stored = match host.identity_key() {
+                        Some(key_path) => crate::config_observer::get_keyring_passphrase(&key_path)
+                            .await
+                            .is_some(),
+                        None => false,
+                    };
+                    this.spawn_ssh_process(&terminal, &host, passphrase_stored)
+                }
+                Err(e) => {
+                    tracing::error!("Host key verification failed for {}: {}", host.alias, e);
+                    terminal.feed(format!("\r\n{}\r\n", e).as_bytes());
+                }
+            }
+        });
     }
 
-    fn spawn_ssh_process(&self, terminal: &vte4::Terminal, host: &SshHost) {
+    /// Runs the tab's shell over the built-in SSH client: VTE gets the master of a
+    /// local pty pair and the engine relays the slave to a remote pty. The tab is
+    /// closed when the shell exits, as with `/usr/bin/ssh`.
+    fn spawn_embedded_shell(
+        &self,
+        terminal: &vte4::Terminal,
+        session_box: &gtk4::Box,
+        host: SshHost,
+        password: Option<String>,
+    ) {
+        let (master, slave) = match crate::engines::shell::open_local_pty() {
+            Ok(pair) => pair,
+            Err(e) => {
+                terminal.feed(format!("\r\n{:#}\r\n", e).as_bytes());
+                return;
+            }
+        };
+        match vte4::Pty::foreign_sync(master, None::<&gio::Cancellable>) {
+            Ok(pty) => terminal.set_pty(Some(&pty)),
+            Err(e) => {
+                terminal.feed(format!("\r\nFailed to attach terminal: {}\r\n", e).as_bytes());
+                return;
+            }
+        }
+
+        let terminal = terminal.clone();
+        let notebook = self.inner.notebook.clone();
+        let session_box = session_box.clone();
+        glib::MainContext::default().spawn_local(async move {
+            match crate::engines::shell::run_shell(&host, password.as_deref(), slave).await {
+                Ok(_) => {
+                    if let Some(i) = notebook.page_num(&session_box) {
+                        notebook.remove_page(Some(i));
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Terminal session to {} failed: {:#}", host.alias, e);
+                    terminal.feed(format!("\r\n{:#}\r\n", e).as_bytes());
+                }
+            }
+        });
+    }
+
+    fn spawn_ssh_process(
+        &self,
+        terminal: &vte4::Terminal,
+        host: &SshHost,
+        passphrase_stored: bool,
+    ) {
         let host_str = host.hostname.clone();
         let user_str = host.user.clone().unwrap_or_else(|| "root".to_string());
         let mut envv: Vec<String> = std::env::vars()
             .map(|(k, v)| format!("{}={}", k, v))
             .collect();
-        if host.identity_file.is_none() {
+        // Without a stored passphrase, let ssh ask for it in the terminal as usual.
+        let identity_key = host.identity_key();
+        if identity_key.is_none() || passphrase_stored {
             // Only needed for the askpass fallback; skip the syscall + allocs otherwise.
             let exe_path = std::env::current_exe()
                 .unwrap_or_default()
@@ -370,6 +587,15 @@ impl AppWindow {
             envv.push(format!("SSH_ASKPASS={}", exe_path));
             envv.push("SSH_ASKPASS_REQUIRE=force".to_string());
             envv.push(format!("RUSTMIUS_ASKPASS_ALIAS={}", host.alias));
+            if let Some(identity_file) = &identity_key {
+                envv.push(format!("RUSTMIUS_ASKPASS_KEY={}", identity_file));
+            }
+            if let Some((socket, token)) =
+                crate::credentials::askpass_grant(&host.alias, identity_key.as_deref())
+            {
+                envv.push(format!("RUSTMIUS_ASKPASS_SOCKET={}", socket.display()));
+                envv.push(format!("RUSTMIUS_ASKPASS_TOKEN={}", token));
+            }
         }
         envv.push("DISPLAY=:0".to_string());
         let env_refs: Vec<&str> = envv.iter().map(|s| s.as_str()).collect();
@@ -379,12 +605,30 @@ impl AppWindow {
             "-p".to_string(),
             port_str,
             "-o".to_string(),
-            "StrictHostKeyChecking=no".to_string(),
+            "StrictHostKeyChecking=yes".to_string(),
         ];
-        if let Some(identity_file) = &host.identity_file {
+        if let Some(identity_file) = &identity_key {
             ssh_args.push("-i".to_string());
             ssh_args.push(identity_file.clone());
         }
+        if let Some(certificate_file) = &host.certificate_file {
+            ssh_args.push("-o".to_string());
+            ssh_args.push(format!("CertificateFile={}", certificate_file));
+        }
+        if let Some(command) = &host.proxy_command {
+            ssh_args.push("-o".to_string());
+            ssh_args.push(format!("ProxyCommand={}", command));
+        }
+        if host.forward_agent {
+            ssh_args.push("-A".to_string());
+        }
+        if host.forward_x11 {
+            ssh_args.push("-X".to_string());
+        }
+        if !host.proxy_jump.is_empty() {
+            ssh_args.push("-J".to_string());
+            ssh_args.push(host.proxy_jump.join(","));
+        }
         ssh_args.push(format!("{}@{}", user_str, host_str));
         let ssh_args_refs: Vec<&str> = ssh_args.iter().map(|s| s.as_str()).collect();
         terminal.spawn_async(
@@ -526,7 +770,36 @@ impl AppWindow {
         notebook.set_current_page(Some(ins_pos));
     }
 
+    fn spawn_tunnels(notebook: &gtk4::Notebook, host: SshHost, password: Option<String>) {
+        if let Some(idx) = Self::find_existing_tab(notebook, "tunnels", &host.alias) {
+            notebook.set_current_page(Some(idx));
+            return;
+        }
+        let tunnels = TunnelManager::new(host.clone(), password);
+        tunnels
+            .container
+            .set_widget_name(&format!("tunnels:{}", host.alias));
+        let label_box = Self::create_tab_label("network-wired-symbolic", &host.alias, {
+            let nb_close = notebook.clone();
+            let child_close = tunnels.container.clone();
+            move || {
+                if let Some(i) = nb_close.page_num(&child_close) {
+                    nb_close.remove_page(Some(i));
+                }
+            }
+        });
+
+        let ins_pos = Self::get_insert_position(notebook);
+        notebook.insert_page(&tunnels.container, Some(&label_box), Some(ins_pos));
+        notebook.set_tab_reorderable(&tunnels.container, true);
+        notebook.set_current_page(Some(ins_pos));
+    }
+
     fn delete_server(&self, host: SshHost) {
+        // Inventory hosts are edited in the inventory, not here.
+        if host.inventory.is_some() {
+            return;
+        }
         let _ = delete_host_from_config(&host.alias);
         let alias = host.alias.clone();
         let this = self.clone();
@@ -536,8 +809,20 @@ impl AppWindow {
         });
     }
 
+    fn test_server(&self, host: SshHost) {
+        let window = self.inner.window.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let password = crate::config_observer::get_keyring_password(&host.alias).await;
+            show_diagnostics_dialog(window.upcast_ref(), host, password);
+        });
+    }
+
     fn edit_server(&self, host: SshHost) {
-        let old_alias = host.alias.clone();
+        // Inventory hosts are edited in the inventory, not here.
+        if host.inventory.is_some() {
+            return;
+        }
+        let original = host.clone();
         let this = self.clone();
         let existing_aliases: Vec<String> = load_hosts()
             .unwrap_or_else(|e| {
@@ -552,21 +837,35 @@ impl AppWindow {
             Some(&host),
             existing_aliases,
             move |new_host, password| {
-                let _ = delete_host_from_config(&old_alias);
-                if add_host_to_config(&new_host).is_ok() {
-                    if !password.is_empty() {
-                        let host_alias = new_host.alias.clone();
-                        let password = zeroize::Zeroizing::new(password);
-                        glib::MainContext::default().spawn_local(async move {
-                            let _ = crate::config_observer::store_keyring_password(
-                                &host_alias,
-                                &password,
-                            )
-                            .await;
-                        });
+                match update_host_in_config(&original, &new_host) {
+                    Err(e) => {
+                        tracing::error!("Failed to update host {}: {}", original.alias, e);
+                        return;
                     }
-                    this.refresh();
+                    Ok(unapplied) if !unapplied.is_empty() => {
+                        gtk4::AlertDialog::builder()
+                            .modal(true)
+                            .message(format!("Some changes to {} don't apply", new_host.alias))
+                            .detail(format!(
+                                "Other Host or Match blocks in the SSH config still set: {}. \
+                                 Edit those blocks to change these values for this host.",
+                                unapplied.join(", ")
+                            ))
+                            .build()
+                            .show(Some(&this.inner.window));
+                    }
+                    Ok(_) => {}
+                }
+                if !password.is_empty() {
+                    let host_alias = new_host.alias.clone();
+                    let password = zeroize::Zeroizing::new(password);
+                    glib::MainContext::default().spawn_local(async move {
+                        let _ =
+                            crate::config_observer::store_keyring_password(&host_alias, &password)
+                                .await;
+                    });
                 }
+                this.refresh();
             },
         );
     }
@@ -642,6 +941,8 @@ impl AppWindow {
 }
 
 pub fn build_ui(app: &gtk4::Application) {
+    crate::ui::host_key_dialog::install_host_key_prompter();
+    crate::ui::auth_dialog::install_auth_prompter();
     AppWindow::new(app);
 }
 